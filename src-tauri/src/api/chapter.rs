@@ -1,6 +1,10 @@
 use tauri::State;
 
-use crate::{entities::Chapter, util::str_error::StringResult};
+use crate::{
+    entities::{Chapter, LibraryChanges, Metadata},
+    util::str_error::StringResult,
+    watcher,
+};
 
 use super::LibState;
 
@@ -33,3 +37,28 @@ pub async fn chapter_page_update(
         .await
         .str_err()
 }
+
+#[tauri::command]
+pub async fn chapter_metadata_update<R: tauri::Runtime>(
+    id: u32,
+    metadata: Metadata,
+    library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
+) -> Result<(), String> {
+    let comic_id = library
+        .access()
+        .await?
+        .update_chapter_metadata(id, metadata)
+        .await
+        .str_err()?;
+
+    // the chapter can have a new number
+    watcher::emit_changes(
+        &app,
+        LibraryChanges {
+            updated_comics: vec![comic_id],
+            ..Default::default()
+        },
+    );
+    Ok(())
+}
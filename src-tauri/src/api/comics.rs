@@ -1,6 +1,10 @@
 use tauri::State;
 
-use crate::{entities::Comic, util::str_error::StringResult};
+use crate::{
+    entities::{Comic, LibraryChanges},
+    util::str_error::StringResult,
+    watcher,
+};
 
 use super::LibState;
 
@@ -24,3 +28,30 @@ pub async fn comic_with_chapters(id: u32, library: State<'_, LibState>) -> Resul
 pub async fn comic(id: u32, library: State<'_, LibState>) -> Result<Comic, String> {
     library.access().await?.database.comic(id).await.str_err()
 }
+
+#[tauri::command]
+pub async fn rescan_library<R: tauri::Runtime>(
+    library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
+) -> Result<LibraryChanges, String> {
+    // the library is only locked to get the scanner, not during the scan
+    let mut lib = library.access().await?.scanner();
+    let changes = lib.update().await.str_err()?;
+    watcher::emit_changes(&app, changes.clone());
+
+    Ok(changes)
+}
+
+#[tauri::command]
+pub async fn rescan_comic<R: tauri::Runtime>(
+    id: u32,
+    library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
+) -> Result<LibraryChanges, String> {
+    let mut lib = library.access().await?.scanner();
+    let dir = lib.database.comic(id).await.str_err()?.dir_path;
+    let changes = lib.update_comics(&[dir]).await.str_err()?;
+    watcher::emit_changes(&app, changes.clone());
+
+    Ok(changes)
+}
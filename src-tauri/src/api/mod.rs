@@ -3,7 +3,7 @@ use std::sync::Arc;
 use tokio::sync::{MappedMutexGuard, Mutex, MutexGuard};
 
 use crate::{
-    library::Library,
+    library::{Library, ScanHandle},
     settings::{LibraryConfig, Settings},
     util::str_error::StringResult,
 };
@@ -11,8 +11,11 @@ use crate::{
 mod chapter;
 mod comics;
 mod orderings;
+mod scan;
 mod settings;
 
+pub use scan::{forward_scan_progress, scan_in_background};
+
 #[derive(Clone)]
 pub struct SettingsState(Arc<Mutex<Settings>>);
 
@@ -26,12 +29,24 @@ impl SettingsState {
     }
 }
 
+#[derive(Clone)]
 pub struct LibState(Arc<Mutex<Option<Library>>>);
 
 impl LibState {
-    pub async fn load_from_settings(settings: &Settings) -> anyhow::Result<Self> {
-        let lib = if let Some(LibraryConfig { path, .. }) = settings.library() {
-            Some(Library::new(path).await?)
+    /// open the selected library, it's scanned for changes after the app has started
+    pub async fn load_from_settings(
+        settings: &Settings,
+        scan: &ScanHandle,
+    ) -> anyhow::Result<Self> {
+        let lib = if let Some(LibraryConfig {
+            path,
+            write_comic_info,
+            ..
+        }) = settings.library()
+        {
+            let mut lib = Library::open(path, scan.clone()).await?;
+            lib.write_comic_info = *write_comic_info;
+            Some(lib)
         } else {
             None
         };
@@ -50,6 +65,11 @@ impl LibState {
     pub async fn access_option(&'_ self) -> MutexGuard<'_, Option<Library>> {
         self.0.lock().await
     }
+
+    /// scan the loaded library with this, so it can still be accessed during the scan
+    pub async fn scanner(&self) -> Option<Library> {
+        self.0.lock().await.as_ref().map(Library::scanner)
+    }
 }
 
 pub fn get_invoke_handler() -> impl Fn(tauri::Invoke<tauri::Wry>) + Send + Sync {
@@ -57,12 +77,18 @@ pub fn get_invoke_handler() -> impl Fn(tauri::Invoke<tauri::Wry>) + Send + Sync
         comics::all_comics,
         comics::comic_with_chapters,
         comics::comic,
+        comics::rescan_library,
+        comics::rescan_comic,
         chapter::chapter,
         chapter::chapter_page_update,
+        chapter::chapter_metadata_update,
+        scan::cancel_scan,
+        scan::scan_report,
         orderings::chapter_orderings,
         orderings::insert_ordering,
         orderings::delete_ordering,
         orderings::update_ordering,
+        orderings::preview_ordering,
         settings::get_settings,
         settings::add_library,
         settings::select_library,
@@ -1,6 +1,10 @@
 use tauri::State;
 
-use crate::{entities::ChapterOrdering, util::str_error::StringResult};
+use crate::{
+    entities::{Chapter, ChapterOrdering, LibraryChanges},
+    util::str_error::StringResult,
+    watcher,
+};
 
 use super::LibState;
 
@@ -19,40 +23,78 @@ pub async fn chapter_orderings(
 }
 
 #[tauri::command]
-pub async fn insert_ordering(
+pub async fn insert_ordering<R: tauri::Runtime>(
     ordering: ChapterOrdering,
     library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
 ) -> Result<(), String> {
-    library
+    let comic_id = library
         .access()
         .await?
-        .database
         .insert_chapter_ordering(ordering)
         .await
-        .str_err()
+        .str_err()?;
+    emit_reordered(&app, comic_id);
+
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_ordering(id: u32, library: State<'_, LibState>) -> Result<(), String> {
-    library
+pub async fn delete_ordering<R: tauri::Runtime>(
+    id: u32,
+    library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
+) -> Result<(), String> {
+    let comic_id = library
         .access()
         .await?
-        .database
         .delete_chapter_ordering(id)
         .await
-        .str_err()
+        .str_err()?;
+    emit_reordered(&app, comic_id);
+
+    Ok(())
 }
 
 #[tauri::command]
-pub async fn update_ordering(
+pub async fn update_ordering<R: tauri::Runtime>(
     ordering: ChapterOrdering,
     library: State<'_, LibState>,
+    app: tauri::AppHandle<R>,
 ) -> Result<(), String> {
-    library
+    let comic_id = library
         .access()
         .await?
-        .database
         .update_chapter_ordering(ordering)
         .await
+        .str_err()?;
+    emit_reordered(&app, comic_id);
+
+    Ok(())
+}
+
+/// the chapters of a comic in the order they would get with the given orderings, without saving them
+#[tauri::command]
+pub async fn preview_ordering(
+    comic_id: u32,
+    orderings: Vec<ChapterOrdering>,
+    library: State<'_, LibState>,
+) -> Result<Vec<Chapter>, String> {
+    library
+        .access()
+        .await?
+        .preview_ordering(comic_id, &orderings)
+        .await
         .str_err()
 }
+
+/// the chapters of the comic have new numbers, so the open chapter lists are reloaded
+fn emit_reordered<R: tauri::Runtime>(app: &tauri::AppHandle<R>, comic_id: u32) {
+    watcher::emit_changes(
+        app,
+        LibraryChanges {
+            updated_comics: vec![comic_id],
+            ..Default::default()
+        },
+    );
+}
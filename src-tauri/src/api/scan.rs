@@ -0,0 +1,50 @@
+use tauri::{AppHandle, Manager, Runtime, State};
+
+use crate::{entities::ScanError, library::ScanHandle, util::str_error::StringResult, watcher};
+
+use super::LibState;
+
+#[tauri::command]
+pub fn cancel_scan(scan: State<'_, ScanHandle>) {
+    scan.cancel();
+}
+
+/// the errors of the last scan, which are stored in the database
+#[tauri::command]
+pub async fn scan_report(library: State<'_, LibState>) -> Result<Vec<ScanError>, String> {
+    library
+        .access()
+        .await?
+        .database
+        .scan_errors()
+        .await
+        .str_err()
+}
+
+/// send the progress of every scan to the frontend with the `scan_progress` event
+pub fn forward_scan_progress<R: Runtime>(app: AppHandle<R>, scan: &ScanHandle) {
+    let mut progress = scan.subscribe();
+
+    tauri::async_runtime::spawn(async move {
+        while progress.changed().await.is_ok() {
+            let p = progress.borrow_and_update().clone();
+            if let Err(e) = app.emit_all("scan_progress", p) {
+                eprintln!("can't send scan_progress event: {e}");
+            }
+        }
+    });
+}
+
+/// scan the loaded library without blocking, so the app can already be used
+pub fn scan_in_background<R: Runtime>(app: AppHandle<R>, library: LibState) {
+    tauri::async_runtime::spawn(async move {
+        let Some(mut lib) = library.scanner().await else {
+            return;
+        };
+
+        match lib.update().await {
+            Ok(changes) => watcher::emit_changes(&app, changes),
+            Err(e) => eprintln!("scanning the library failed: {e:#}"),
+        }
+    });
+}
@@ -1,11 +1,11 @@
-use std::path::Path;
-
 use tauri::Manager;
 
 use crate::{
-    library::Library,
+    entities::LibraryChanges,
+    library::{Library, ScanHandle},
     settings::{LibraryConfig, Settings},
     util::str_error::StringResult,
+    watcher,
 };
 
 use super::SettingsState;
@@ -62,31 +62,28 @@ async fn load_library<R: tauri::Runtime>(
     app: tauri::AppHandle<R>,
 ) -> Result<(), String> {
     let path = lib.path.clone();
+    let config = lib.clone();
 
     // only allow absolute paths here
     if path.is_relative() {
         return Ok(());
     }
 
-    let mut lib = library.access_option().await;
-    let tmp = std::thread::spawn(move || create_new_library(path))
-        .join()
-        .expect("thread panicked again :(")?;
-    *lib = Some(tmp);
+    let scan = app.state::<ScanHandle>().inner().clone();
+    let mut tmp = Library::open(path, scan).await.str_err()?;
+    tmp.write_comic_info = config.write_comic_info;
+    watcher::start(&mut tmp, &config, library.inner().clone(), app.clone());
+    *library.access_option().await = Some(tmp);
 
     // TODO: maybe give the comics with the event for less communication errors
-    app.emit_all("comics_reloaded", ()).str_err()?;
+    // no changes in the payload, because every comic has to be reloaded
+    app.emit_all("comics_reloaded", LibraryChanges::default())
+        .str_err()?;
+    super::scan_in_background(app, library.inner().clone());
 
     Ok(())
 }
 
-/// needs to be run on another thread because
-/// some kind of higher order lifetime error
-#[tokio::main]
-async fn create_new_library<P: AsRef<Path>>(p: P) -> Result<Library, String> {
-    Library::new(p).await.str_err()
-}
-
 #[tauri::command]
 pub async fn delete_library<R: tauri::Runtime>(
     id: usize,
@@ -114,7 +111,8 @@ pub async fn delete_library<R: tauri::Runtime>(
                 let mut lib = library.access_option().await;
                 *lib = None;
                 sett.selected_library = None;
-                app.emit_all("comics_reloaded", ()).str_err()?;
+                app.emit_all("comics_reloaded", LibraryChanges::default())
+                    .str_err()?;
             }
             _ => (),
         }
@@ -140,9 +138,13 @@ pub async fn update_library<R: tauri::Runtime>(
     drop(sett);
 
     if let Some(lib) = lib
-     && lib.id == id
-     && lib.path != old_path {
-        load_library(&lib, library, app).await?;
+     && lib.id == id {
+        if lib.path != old_path {
+            load_library(&lib, library, app).await?;
+        } else if let Some(loaded) = library.access_option().await.as_mut() {
+            loaded.write_comic_info = lib.write_comic_info;
+            watcher::schedule_rescan(loaded, lib.rescan_interval, library.inner().clone(), app);
+        }
     }
     persist(settings).await
 }
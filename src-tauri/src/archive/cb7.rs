@@ -0,0 +1,80 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, Read},
+    path::Path,
+};
+
+use anyhow::{bail, Result};
+use sevenz_rust::{Password, SevenZReader};
+
+use super::{prefixes_in_order, ComicArchive};
+
+/// 7z archive containing the pages
+pub struct Cb7 {
+    reader: SevenZReader<File>,
+}
+
+impl Cb7 {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            reader: SevenZReader::open(path, Password::empty())?,
+        })
+    }
+}
+
+impl ComicArchive for Cb7 {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        Ok(self
+            .reader
+            .archive()
+            .files
+            .iter()
+            .filter(|f| !f.is_directory())
+            .map(|f| f.name().to_owned())
+            .collect())
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let mut content = None;
+
+        // entries of solid archives can only be decoded in order
+        self.reader.for_each_entries(|entry, reader| {
+            if entry.name() != name {
+                // the data of skipped entries still has to be consumed
+                io::copy(reader, &mut io::sink())?;
+                return Ok(true);
+            }
+
+            let mut buf = Vec::with_capacity(entry.size() as usize);
+            reader.read_to_end(&mut buf)?;
+            content = Some(buf);
+            Ok(false)
+        })?;
+
+        match content {
+            Some(c) => Ok(c),
+            None => bail!("page {name} doesn't exist in the archive"),
+        }
+    }
+
+    /// the entries of solid archives can only be decompressed from the start,
+    /// so all prefixes are read in a single pass
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        let wanted = names.iter().map(String::as_str).collect::<HashSet<_>>();
+        let mut found = HashMap::with_capacity(names.len());
+
+        self.reader.for_each_entries(|entry, reader| {
+            if wanted.contains(entry.name()) {
+                let mut prefix = Vec::with_capacity(len);
+                reader.take(len as u64).read_to_end(&mut prefix)?;
+                found.insert(entry.name().to_owned(), prefix);
+            }
+            // the rest of the entry has to be skipped to get to the next one
+            io::copy(reader, &mut io::sink())?;
+            Ok(found.len() < wanted.len())
+        })?;
+
+        prefixes_in_order(names, found)
+    }
+}
@@ -0,0 +1,71 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+
+use super::{prefixes_in_order, ComicArchive};
+
+/// rar archive containing the pages
+pub struct Cbr {
+    path: PathBuf,
+}
+
+impl Cbr {
+    pub fn open(path: &Path) -> Result<Self> {
+        // unrar works on paths, so the archive is only opened on access
+        Ok(Self { path: path.into() })
+    }
+}
+
+impl ComicArchive for Cbr {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for entry in unrar::Archive::new(&self.path).open_for_listing()? {
+            let entry = entry?;
+            if entry.is_file() {
+                names.push(entry.filename.to_string_lossy().into_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let mut archive = unrar::Archive::new(&self.path).open_for_processing()?;
+
+        // rar archives can only be read sequentially, so skip to the wanted entry
+        while let Some(header) = archive.read_header()? {
+            if header.entry().filename == Path::new(name) {
+                let (content, _) = header.read()?;
+                return Ok(content);
+            }
+            archive = header.skip()?;
+        }
+
+        bail!("page {name} doesn't exist in {}", self.path.display())
+    }
+
+    /// entries can't be read partially and solid archives only from the start,
+    /// so the wanted entries are read in a single pass
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        let wanted = names.iter().map(String::as_str).collect::<HashSet<_>>();
+        let mut found = HashMap::with_capacity(names.len());
+        let mut archive = unrar::Archive::new(&self.path).open_for_processing()?;
+
+        while let Some(header) = archive.read_header()? {
+            let name = header.entry().filename.to_string_lossy().into_owned();
+            archive = if wanted.contains(name.as_str()) {
+                let (mut content, rest) = header.read()?;
+                content.truncate(len);
+                found.insert(name, content);
+                rest
+            } else {
+                header.skip()?
+            };
+        }
+
+        prefixes_in_order(names, found)
+    }
+}
@@ -0,0 +1,73 @@
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+
+use super::{prefixes_in_order, ComicArchive};
+
+/// tar archive containing the pages
+pub struct Cbt {
+    path: PathBuf,
+}
+
+impl Cbt {
+    pub fn open(path: &Path) -> Result<Self> {
+        // tar archives can only be iterated once, so they are reopened on access
+        Ok(Self { path: path.into() })
+    }
+
+    fn archive(&self) -> Result<tar::Archive<BufReader<File>>> {
+        Ok(tar::Archive::new(BufReader::new(File::open(&self.path)?)))
+    }
+}
+
+impl ComicArchive for Cbt {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for entry in self.archive()?.entries()? {
+            let entry = entry?;
+            if entry.header().entry_type().is_file() {
+                names.push(entry.path()?.to_string_lossy().into_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        for entry in self.archive()?.entries()? {
+            let mut entry = entry?;
+            if entry.path()? != Path::new(name) {
+                continue;
+            }
+
+            let mut content = Vec::with_capacity(entry.size() as usize);
+            entry.read_to_end(&mut content)?;
+            return Ok(content);
+        }
+
+        bail!("page {name} doesn't exist in {}", self.path.display())
+    }
+
+    /// a tar can only be read from the start, so all prefixes are read in a single pass
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        let wanted = names.iter().map(String::as_str).collect::<HashSet<_>>();
+        let mut found = HashMap::with_capacity(names.len());
+
+        for entry in self.archive()?.entries()? {
+            let entry = entry?;
+            let name = entry.path()?.to_string_lossy().into_owned();
+            if wanted.contains(name.as_str()) {
+                let mut prefix = Vec::with_capacity(len);
+                entry.take(len as u64).read_to_end(&mut prefix)?;
+                found.insert(name, prefix);
+            }
+        }
+
+        prefixes_in_order(names, found)
+    }
+}
@@ -0,0 +1,117 @@
+use std::{
+    fs::{self, File},
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use anyhow::Result;
+use zip::{write::FileOptions, ZipArchive, ZipWriter};
+
+use super::ComicArchive;
+use crate::entities::Metadata;
+use crate::metadata::comic_info;
+
+/// zip archive containing the pages
+pub struct Cbz {
+    zip: ZipArchive<BufReader<File>>,
+}
+
+impl Cbz {
+    pub fn open(path: &Path) -> Result<Self> {
+        let file = BufReader::new(File::open(path)?);
+        Ok(Self {
+            zip: ZipArchive::new(file)?,
+        })
+    }
+}
+
+impl ComicArchive for Cbz {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        let mut names = vec![];
+        for i in 0..self.zip.len() {
+            let file = self.zip.by_index_raw(i)?;
+            if file.is_file() {
+                names.push(file.name().to_owned());
+            }
+        }
+
+        Ok(names)
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let mut file = self.zip.by_name(name)?;
+        let mut content = Vec::with_capacity(file.size() as usize);
+        file.read_to_end(&mut content)?;
+
+        Ok(content)
+    }
+
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        names
+            .iter()
+            .map(|name| {
+                let mut prefix = Vec::with_capacity(len);
+                self.zip
+                    .by_name(name)?
+                    .take(len as u64)
+                    .read_to_end(&mut prefix)?;
+                Ok(prefix)
+            })
+            .collect()
+    }
+
+    fn comment(&mut self) -> Result<Option<String>> {
+        let comment = self.zip.comment();
+        Ok((!comment.is_empty()).then(|| String::from_utf8_lossy(comment).into_owned()))
+    }
+}
+
+/// replace the `ComicInfo.xml` inside of the zip with one containing the metadata,
+/// the new zip is written to a temporary file first which then replaces the original,
+/// so the chapter is never left half written
+pub fn write_comic_info(path: &Path, metadata: &Metadata) -> Result<()> {
+    let mut cbz = Cbz::open(path)?;
+    let existing = cbz
+        .file_names()?
+        .into_iter()
+        .find(|n| comic_info::is_comic_info(n));
+    let existing_xml = match &existing {
+        Some(name) => Some(String::from_utf8(cbz.read_file(name)?)?),
+        None => None,
+    };
+    let xml = comic_info::write(metadata, existing_xml.as_deref())?;
+
+    let tmp_path = path.with_extension("cbz.tmp");
+    if let Err(e) = copy_with_comic_info(&mut cbz.zip, &tmp_path, existing.as_deref(), &xml) {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(e);
+    }
+
+    Ok(fs::rename(tmp_path, path)?)
+}
+
+/// copy every file of the zip without recompressing it, except the old `ComicInfo.xml`
+fn copy_with_comic_info(
+    zip: &mut ZipArchive<BufReader<File>>,
+    target: &Path,
+    old_comic_info: Option<&str>,
+    xml: &str,
+) -> Result<()> {
+    let mut writer = ZipWriter::new(BufWriter::new(File::create(target)?));
+    writer.set_comment(String::from_utf8_lossy(zip.comment()));
+
+    for i in 0..zip.len() {
+        let file = zip.by_index_raw(i)?;
+        if Some(file.name()) != old_comic_info {
+            writer.raw_copy_file(file)?;
+        }
+    }
+
+    writer.start_file(comic_info::FILE_NAME, FileOptions::default())?;
+    writer.write_all(xml.as_bytes())?;
+
+    let file = writer.finish()?.into_inner().map_err(|e| e.into_error())?;
+    file.sync_all()?;
+
+    Ok(())
+}
@@ -0,0 +1,152 @@
+use std::{collections::HashMap, path::Path};
+
+use anyhow::{Context, Result};
+use roxmltree::{Document, ParsingOptions};
+
+use super::{cbz::Cbz, ComicArchive};
+use crate::entities::Metadata;
+
+const CONTAINER_PATH: &str = "META-INF/container.xml";
+const DC_NAMESPACE: &str = "http://purl.org/dc/elements/1.1/";
+const XLINK_NAMESPACE: &str = "http://www.w3.org/1999/xlink";
+
+/// (fixed layout) epub, whose spine consists of image pages
+pub struct Epub {
+    zip: Cbz,
+    pages: Vec<String>,
+    metadata: Metadata,
+}
+
+impl Epub {
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut zip = Cbz::open(path)?;
+
+        let container = String::from_utf8(zip.read_file(CONTAINER_PATH)?)?;
+        let opf_path = Document::parse(&container)?
+            .descendants()
+            .find(|n| n.has_tag_name("rootfile"))
+            .and_then(|n| n.attribute("full-path"))
+            .context("epub container doesn't reference a package document")?
+            .to_owned();
+
+        let opf = String::from_utf8(zip.read_file(&opf_path)?)?;
+        let opf = Document::parse(&opf)?;
+
+        let mut epub = Self {
+            pages: vec![],
+            metadata: read_metadata(&opf),
+            zip,
+        };
+        epub.pages = epub.spine_images(&opf, &opf_path)?;
+
+        Ok(epub)
+    }
+
+    /// get the images of the spine items in reading order,
+    /// items without images (e.g. text only pages) are skipped
+    fn spine_images(&mut self, opf: &Document, opf_path: &str) -> Result<Vec<String>> {
+        // id -> (path, media type)
+        let manifest: HashMap<_, _> = opf
+            .descendants()
+            .filter(|n| n.has_tag_name("item"))
+            .filter_map(|n| {
+                let href = resolve_href(opf_path, n.attribute("href")?);
+                Some((n.attribute("id")?, (href, n.attribute("media-type")?)))
+            })
+            .collect();
+
+        let spine_items = opf
+            .descendants()
+            .filter(|n| n.has_tag_name("itemref"))
+            .filter_map(|n| manifest.get(n.attribute("idref")?));
+
+        let mut pages = vec![];
+        for (path, media_type) in spine_items {
+            if media_type.starts_with("image/") {
+                pages.push(path.clone());
+            } else if let Some(image) = self.image_of_document(path)? {
+                pages.push(image);
+            }
+        }
+
+        Ok(pages)
+    }
+
+    /// get the first image which is referenced inside of a (x)html spine item
+    fn image_of_document(&mut self, path: &str) -> Result<Option<String>> {
+        let content = String::from_utf8(self.zip.read_file(path)?)?;
+        let options = ParsingOptions {
+            allow_dtd: true,
+            ..Default::default()
+        };
+        let doc = Document::parse_with_options(&content, options)?;
+
+        let src = doc.descendants().find_map(|n| match n.tag_name().name() {
+            "img" => n.attribute("src"),
+            "image" => n
+                .attribute((XLINK_NAMESPACE, "href"))
+                .or_else(|| n.attribute("href")),
+            _ => None,
+        });
+
+        Ok(src.map(|s| resolve_href(path, s)))
+    }
+}
+
+impl ComicArchive for Epub {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        self.zip.file_names()
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        self.zip.read_file(name)
+    }
+
+    /// the spine defines the reading order instead of the file names
+    fn page_names(&mut self) -> Result<Vec<String>> {
+        Ok(self.pages.clone())
+    }
+
+    fn metadata(&mut self) -> Result<Metadata> {
+        Ok(self.metadata.clone())
+    }
+}
+
+fn read_metadata(opf: &Document) -> Metadata {
+    let dc_text = |name: &str| {
+        opf.descendants()
+            .find(|n| n.has_tag_name((DC_NAMESPACE, name)))
+            .and_then(|n| n.text())
+            .map(|t| t.trim().to_owned())
+            .filter(|t| !t.is_empty())
+    };
+
+    Metadata {
+        title: dc_text("title"),
+        author: dc_text("creator"),
+        language: dc_text("language"),
+        summary: dc_text("description"),
+        ..Default::default()
+    }
+}
+
+/// resolve a (relative) reference from the file at `base` to a path inside of the archive
+fn resolve_href(base: &str, href: &str) -> String {
+    let href = href.split('#').next().unwrap_or_default();
+    let href = percent_encoding::percent_decode_str(href).decode_utf8_lossy();
+
+    let mut parts = base.split('/').collect::<Vec<_>>();
+    parts.pop(); // remove the file name of the base
+
+    for part in href.split('/') {
+        match part {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            p => parts.push(p),
+        }
+    }
+
+    parts.join("/")
+}
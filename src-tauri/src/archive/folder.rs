@@ -0,0 +1,90 @@
+use std::{
+    fs::{self, File},
+    io::Read,
+    path::{Path, PathBuf},
+};
+
+use anyhow::{bail, Result};
+
+use super::{ComicArchive, EntryKind};
+
+/// directory with the pages as plain image files
+pub struct Folder {
+    path: PathBuf,
+}
+
+impl Folder {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self { path: path.into() })
+    }
+
+    /// only allow reading files directly inside of the folder
+    fn page_path(&self, name: &str) -> Result<PathBuf> {
+        if Path::new(name).file_name().map(|n| n.to_string_lossy()) != Some(name.into()) {
+            bail!("invalid page name {name}");
+        }
+
+        Ok(self.path.join(name))
+    }
+}
+
+impl ComicArchive for Folder {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        Ok(visible_entries(&self.path)?
+            .into_iter()
+            .filter(|p| p.is_file())
+            .filter_map(|p| Some(p.file_name()?.to_string_lossy().into_owned()))
+            .collect())
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        Ok(fs::read(self.page_path(name)?)?)
+    }
+
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        names
+            .iter()
+            .map(|name| {
+                let mut prefix = Vec::with_capacity(len);
+                File::open(self.page_path(name)?)?
+                    .take(len as u64)
+                    .read_to_end(&mut prefix)?;
+                Ok(prefix)
+            })
+            .collect()
+    }
+}
+
+/// files which are stored next to the pages by taggers or the file manager, in lowercase
+const SIDECAR_FILES: [&str; 3] = ["comicinfo.xml", "thumbs.db", "desktop.ini"];
+
+/// checks if the directory only consists of images and can therefore be used as a chapter,
+/// sidecar files like the `ComicInfo.xml` are allowed next to them
+pub fn is_image_folder(path: &Path) -> bool {
+    visible_entries(path).is_ok_and(|entries| {
+        let mut has_image = false;
+        let only_pages = entries.iter().all(|p| {
+            let name = p.file_name().unwrap_or_default().to_string_lossy();
+            let is_image = EntryKind::from_name(&name) == EntryKind::Image;
+            has_image |= is_image;
+            p.is_file() && (is_image || SIDECAR_FILES.contains(&name.to_lowercase().as_str()))
+        });
+
+        has_image && only_pages
+    })
+}
+
+fn visible_entries(path: &Path) -> Result<Vec<PathBuf>> {
+    let mut entries = vec![];
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if !path
+            .file_name()
+            .is_some_and(|n| n.to_string_lossy().starts_with('.'))
+        {
+            entries.push(path);
+        }
+    }
+
+    Ok(entries)
+}
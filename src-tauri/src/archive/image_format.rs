@@ -0,0 +1,85 @@
+/// image formats which can be shown as pages
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImageFormat {
+    Jpeg,
+    Png,
+    Gif,
+    WebP,
+    Avif,
+    Jxl,
+    Bmp,
+}
+
+impl ImageFormat {
+    /// detect the format from the magic bytes at the start of the file
+    pub fn sniff(content: &[u8]) -> Option<Self> {
+        match content {
+            [0xFF, 0xD8, 0xFF, ..] => Some(Self::Jpeg),
+            [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some(Self::Png),
+            [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some(Self::Gif),
+            [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => Some(Self::WebP),
+            // bare codestream or the iso bmff container
+            [0xFF, 0x0A, ..] => Some(Self::Jxl),
+            [0, 0, 0, 0x0C, b'J', b'X', b'L', b' ', 0x0D, 0x0A, 0x87, 0x0A, ..] => Some(Self::Jxl),
+            [_, _, _, _, b'f', b't', b'y', b'p', ..] if is_avif(content) => Some(Self::Avif),
+            [b'B', b'M', ..] => Some(Self::Bmp),
+            _ => None,
+        }
+    }
+
+    pub fn mime_type(self) -> &'static str {
+        match self {
+            Self::Jpeg => "image/jpeg",
+            Self::Png => "image/png",
+            Self::Gif => "image/gif",
+            Self::WebP => "image/webp",
+            Self::Avif => "image/avif",
+            Self::Jxl => "image/jxl",
+            Self::Bmp => "image/bmp",
+        }
+    }
+}
+
+/// checks the brands of the `ftyp` box, which is at the start of every avif file
+fn is_avif(content: &[u8]) -> bool {
+    let box_size = u32::from_be_bytes([content[0], content[1], content[2], content[3]]) as usize;
+    let brands = content
+        .get(8..box_size.min(content.len()))
+        .unwrap_or_default();
+
+    // the major brand is followed by the version, which would be misread as a brand
+    brands
+        .chunks_exact(4)
+        .enumerate()
+        .filter(|(i, _)| *i != 1)
+        .any(|(_, b)| b == b"avif" || b == b"avis")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_formats() {
+        let avif = b"\0\0\0\x1cftypavif\0\0\0\0avifmif1miaf";
+        let heic = b"\0\0\0\x18ftypheic\0\0\0\0mif1heic";
+
+        let cases: [(&[u8], Option<ImageFormat>); 11] = [
+            (b"\xFF\xD8\xFF\xE0\0\x10JFIF", Some(ImageFormat::Jpeg)),
+            (b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR", Some(ImageFormat::Png)),
+            (b"GIF89a\x01\0", Some(ImageFormat::Gif)),
+            (b"RIFF\x24\0\0\0WEBPVP8 ", Some(ImageFormat::WebP)),
+            (avif, Some(ImageFormat::Avif)),
+            (b"\xFF\x0A\xFA", Some(ImageFormat::Jxl)),
+            (b"\0\0\0\x0cJXL \r\n\x87\n", Some(ImageFormat::Jxl)),
+            (b"BM\x36\0\0\0", Some(ImageFormat::Bmp)),
+            (heic, None),
+            (b"<?xml version=\"1.0\"?><ComicInfo/>", None),
+            (b"", None),
+        ];
+
+        for (content, format) in cases {
+            assert_eq!(ImageFormat::sniff(content), format, "{content:?}");
+        }
+    }
+}
@@ -0,0 +1,654 @@
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use anyhow::{bail, Context, Result};
+use sha2::{Digest, Sha256};
+
+use crate::entities::Metadata;
+use crate::metadata::{comic_book_info, comic_info};
+use crate::util::natural_sort::natural_cmp;
+
+mod cb7;
+mod cbr;
+mod cbt;
+mod cbz;
+mod epub;
+mod folder;
+mod image_format;
+mod page_cache;
+mod pdf;
+
+pub use image_format::ImageFormat;
+pub use pdf::set_resource_dir;
+
+/// file extensions of the image formats which can be shown as pages
+const IMAGE_EXTENSIONS: [&str; 8] = ["jpg", "jpeg", "png", "gif", "webp", "avif", "jxl", "bmp"];
+
+/// how many bytes at the start of a file are enough to detect its image format
+const SNIFF_BYTES: usize = 64;
+
+/// how much of the start and of the end of a chapter file is hashed for its fingerprint
+const FINGERPRINT_BYTES: u64 = 64 * 1024;
+
+/// A container format which stores the pages of a chapter
+pub trait ComicArchive {
+    /// names of all files inside of the archive, directories are left out
+    fn file_names(&mut self) -> Result<Vec<String>>;
+
+    /// read the content of the file with the given name
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>>;
+
+    /// read up to `len` bytes from the start of each of the files, in the order of the names,
+    /// formats which can't stop early read the whole files
+    fn read_prefixes(&mut self, names: &[String], len: usize) -> Result<Vec<Vec<u8>>> {
+        names
+            .iter()
+            .map(|name| {
+                let mut content = self.read_file(name)?;
+                content.truncate(len);
+                Ok(content)
+            })
+            .collect()
+    }
+
+    /// names of the pages inside the archive in reading order,
+    /// files which aren't images (e.g. `ComicInfo.xml` or `Thumbs.db`) are skipped
+    fn page_names(&mut self) -> Result<Vec<String>> {
+        // files with an image extension or without any are checked by their magic bytes
+        let candidates = self
+            .file_names()?
+            .into_iter()
+            .filter(|n| EntryKind::from_name(n) != EntryKind::Other)
+            .collect::<Vec<_>>();
+        let prefixes = self.read_prefixes(&candidates, SNIFF_BYTES)?;
+
+        let pages = candidates
+            .into_iter()
+            .zip(prefixes)
+            .filter(|(_, prefix)| ImageFormat::sniff(prefix).is_some())
+            .map(|(name, _)| name)
+            .collect();
+
+        Ok(natural_order(pages))
+    }
+
+    /// comment of the whole archive, only some formats support one
+    fn comment(&mut self) -> Result<Option<String>> {
+        Ok(None)
+    }
+
+    /// metadata stored inside of the archive, which is read from the `ComicInfo.xml`
+    /// or the ComicBookInfo in the archive comment if there is none
+    fn metadata(&mut self) -> Result<Metadata> {
+        if let Some(name) = self
+            .file_names()?
+            .into_iter()
+            .find(|n| comic_info::is_comic_info(n))
+        {
+            let xml = String::from_utf8(self.read_file(&name)?)?;
+            return comic_info::parse(&xml);
+        }
+
+        match self.comment()? {
+            Some(comment) if comic_book_info::is_comic_book_info(&comment) => {
+                comic_book_info::parse(&comment)
+            }
+            _ => Ok(Metadata::default()),
+        }
+    }
+}
+
+/// everything about a chapter, which is read from its archive when it's added to the library
+pub struct ArchiveInfo {
+    pub pages: u32,
+    pub metadata: Metadata,
+    /// the metadata couldn't be read and the defaults are used instead
+    pub metadata_error: Option<anyhow::Error>,
+}
+
+/// a single page read from an archive
+pub struct Page {
+    pub name: String,
+    pub content: Vec<u8>,
+    /// format detected from the content, `None` when it's no supported image
+    pub format: Option<ImageFormat>,
+}
+
+/// checks if the file or directory at the path can be opened as a chapter
+pub fn is_supported(path: &Path) -> bool {
+    ArchiveKind::from_path(path).is_some()
+}
+
+/// open the archive at the path with the reader fitting its file type
+pub fn open<P: AsRef<Path>>(path: P) -> Result<Box<dyn ComicArchive>> {
+    let path = path.as_ref();
+    let kind = ArchiveKind::from_path(path)
+        .with_context(|| format!("unsupported chapter format: {}", path.display()))?;
+
+    Ok(match kind {
+        ArchiveKind::Zip => Box::new(cbz::Cbz::open(path)?),
+        ArchiveKind::Rar => Box::new(cbr::Cbr::open(path)?),
+        ArchiveKind::SevenZip => Box::new(cb7::Cb7::open(path)?),
+        ArchiveKind::Tar => Box::new(cbt::Cbt::open(path)?),
+        ArchiveKind::Folder => Box::new(folder::Folder::open(path)?),
+        ArchiveKind::Pdf => Box::new(pdf::Pdf::open(path)?),
+        ArchiveKind::Epub => Box::new(epub::Epub::open(path)?),
+    })
+}
+
+/// get the page count and metadata of the chapter at the path
+pub fn inspect<P: AsRef<Path>>(path: P) -> Result<ArchiveInfo> {
+    let path = path.as_ref();
+    let mut archive = open(path)?;
+
+    // the page names are kept, so reading the chapter right after doesn't search it again
+    let pages = page_cache::page_names(path, archive.as_mut())?.len() as u32;
+    // broken metadata shouldn't prevent the chapter from being read
+    let (metadata, metadata_error) = match archive.metadata() {
+        Ok(metadata) => (metadata, None),
+        Err(e) => (Metadata::default(), Some(e.context("invalid metadata"))),
+    };
+
+    Ok(ArchiveInfo {
+        pages,
+        metadata,
+        metadata_error,
+    })
+}
+
+/// identifies the content of the chapter at the path, so it's recognized after a rename or move,
+/// only the size, start and end of files are hashed, which contain the file list of most formats
+pub fn fingerprint<P: AsRef<Path>>(path: P) -> Result<String> {
+    let path = path.as_ref();
+    let mut hasher = Sha256::new();
+
+    if path.is_dir() {
+        // the pages of a folder are identified by their names and sizes
+        for name in natural_order(folder::Folder::open(path)?.file_names()?) {
+            let size = path.join(&name).metadata()?.len();
+            hasher.update(name.as_bytes());
+            hasher.update(size.to_le_bytes());
+        }
+    } else {
+        let mut file = File::open(path)?;
+        let size = file.metadata()?.len();
+        hasher.update(size.to_le_bytes());
+
+        let mut bytes = vec![];
+        file.by_ref()
+            .take(FINGERPRINT_BYTES)
+            .read_to_end(&mut bytes)?;
+        // the end doesn't overlap with the start, which is already hashed
+        let end = size
+            .saturating_sub(FINGERPRINT_BYTES)
+            .max(FINGERPRINT_BYTES);
+        if end < size {
+            file.seek(SeekFrom::Start(end))?;
+            file.read_to_end(&mut bytes)?;
+        }
+        hasher.update(&bytes);
+    }
+
+    Ok(format!("{:x}", hasher.finalize()))
+}
+
+/// store the metadata as the `ComicInfo.xml` of the chapter at the path,
+/// only zip archives can be rewritten, so the other formats fail
+pub fn write_metadata<P: AsRef<Path>>(path: P, metadata: &Metadata) -> Result<()> {
+    let path = path.as_ref();
+    match ArchiveKind::from_path(path) {
+        Some(ArchiveKind::Zip) => cbz::write_comic_info(path, metadata),
+        _ => bail!("ComicInfo.xml can only be written into CBZ files"),
+    }
+}
+
+/// read the page with the (zero based) index from the chapter at the path by its name,
+/// the archive is only searched for the pages the first time
+pub fn read_page<P: AsRef<Path>>(path: P, index: usize) -> Result<Page> {
+    let path = path.as_ref();
+    let mut archive = open(path)?;
+    let Some(name) = page_cache::page_names(path, archive.as_mut())?
+        .get(index)
+        .cloned()
+    else {
+        bail!("page {} doesn't exist", index + 1);
+    };
+
+    let content = archive.read_file(&name)?;
+    let format = ImageFormat::sniff(&content);
+
+    Ok(Page {
+        name,
+        content,
+        format,
+    })
+}
+
+/// the prefixes found by a single pass through an archive in the order of the names
+fn prefixes_in_order(
+    names: &[String],
+    mut found: HashMap<String, Vec<u8>>,
+) -> Result<Vec<Vec<u8>>> {
+    names
+        .iter()
+        .map(|name| {
+            found
+                .remove(name)
+                .with_context(|| format!("file {name} doesn't exist in the archive"))
+        })
+        .collect()
+}
+
+/// sort the page names of an archive by their natural order,
+/// because the order they are stored in often isn't the reading order
+fn natural_order(mut names: Vec<String>) -> Vec<String> {
+    names.sort_by(|a, b| natural_cmp(a, b));
+    names
+}
+
+/// what a file inside of an archive probably is, judging by its name
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum EntryKind {
+    Image,
+    Unknown,
+    Other,
+}
+
+impl EntryKind {
+    fn from_name(name: &str) -> Self {
+        let path = Path::new(name);
+
+        // resource forks of macOS and hidden files like `.DS_Store`
+        let is_hidden = path.components().any(|c| {
+            let c = c.as_os_str().to_string_lossy();
+            c == "__MACOSX" || c.starts_with('.')
+        });
+
+        match path.extension() {
+            _ if is_hidden => Self::Other,
+            Some(ext)
+                if IMAGE_EXTENSIONS.contains(&ext.to_string_lossy().to_lowercase().as_str()) =>
+            {
+                Self::Image
+            }
+            Some(_) => Self::Other,
+            None => Self::Unknown,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ArchiveKind {
+    Zip,
+    Rar,
+    SevenZip,
+    Tar,
+    Folder,
+    Pdf,
+    Epub,
+}
+
+impl ArchiveKind {
+    fn from_path(path: &Path) -> Option<Self> {
+        if path.is_dir() {
+            return folder::is_image_folder(path).then_some(Self::Folder);
+        }
+
+        let ext = path.extension()?.to_string_lossy().to_lowercase();
+
+        match ext.as_str() {
+            "cbz" => Some(Self::Zip),
+            "cbr" => Some(Self::Rar),
+            "cb7" => Some(Self::SevenZip),
+            "cbt" => Some(Self::Tar),
+            "pdf" => Some(Self::Pdf),
+            "epub" => Some(Self::Epub),
+            _ => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, io::Write};
+
+    const PNG: &[u8] = b"\x89PNG\r\n\x1a\n";
+
+    /// pages in reading order, they are stored in reverse to check the sorting
+    const PAGES: [(&str, &[u8]); 3] = [
+        ("page1.png", b"\x89PNG\r\n\x1a\nfirst page"),
+        ("page2.png", b"\x89PNG\r\n\x1a\nsecond page"),
+        ("page10", b"\x89PNG\r\n\x1a\nthird page without extension"),
+    ];
+
+    /// files which aren't pages and are stored with them
+    const JUNK: [(&str, &[u8]); 5] = [
+        (
+            "ComicInfo.xml",
+            b"<ComicInfo><Series>Series</Series></ComicInfo>",
+        ),
+        ("Thumbs.db", b"\0"),
+        ("__MACOSX/._page1.png", PNG),
+        ("release", b"no image"),
+        ("cover.jpg", b"<html>not a jpeg</html>"),
+    ];
+
+    fn create_cbz(path: &Path) -> Result<()> {
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        for (name, content) in PAGES.into_iter().rev().chain(JUNK) {
+            zip.start_file(name, Default::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn create_cb7(path: &Path) -> Result<()> {
+        let mut sz = sevenz_rust::SevenZWriter::create(path)?;
+        for (name, content) in PAGES.into_iter().rev().chain(JUNK) {
+            let mut entry = sevenz_rust::SevenZArchiveEntry::new();
+            entry.name = name.to_string();
+            sz.push_archive_entry(entry, Some(content))?;
+        }
+        sz.finish()?;
+        Ok(())
+    }
+
+    fn create_cbt(path: &Path) -> Result<()> {
+        let mut tar = tar::Builder::new(File::create(path)?);
+        for (name, content) in PAGES.into_iter().rev().chain(JUNK) {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(content.len() as u64);
+            header.set_mode(0o644);
+            tar.append_data(&mut header, name, content)?;
+        }
+        tar.finish()?;
+        Ok(())
+    }
+
+    /// there's no rar writer, so the archive is put together by hand,
+    /// in the RAR 4 format with every entry stored uncompressed
+    fn create_cbr(path: &Path) -> Result<()> {
+        fn crc32(bytes: &[u8]) -> u32 {
+            !bytes.iter().fold(!0, |crc, &b| {
+                (0..8).fold(crc ^ b as u32, |c, _| {
+                    (c >> 1) ^ (0xEDB8_8320 & (c & 1).wrapping_neg())
+                })
+            })
+        }
+        // the crc of a block header covers everything after the crc itself
+        fn block(header: &[u8]) -> Vec<u8> {
+            let crc = crc32(header) as u16;
+            [&crc.to_le_bytes()[..], header].concat()
+        }
+
+        let mut rar = b"Rar!\x1a\x07\x00".to_vec();
+        rar.extend(block(&[0x73, 0, 0, 13, 0, 0, 0, 0, 0, 0, 0]));
+        for (name, content) in PAGES.into_iter().rev().chain(JUNK) {
+            let size = (content.len() as u32).to_le_bytes();
+            let mut header = vec![0x74, 0x00, 0x80];
+            header.extend((32 + name.len() as u16).to_le_bytes());
+            header.extend(size);
+            header.extend(size);
+            // unix host, stored with version 2.0
+            header.push(3);
+            header.extend(crc32(content).to_le_bytes());
+            header.extend([0, 0, 0x21, 0x58, 20, 0x30]);
+            header.extend((name.len() as u16).to_le_bytes());
+            header.extend(0o100644u32.to_le_bytes());
+            header.extend(name.as_bytes());
+            rar.extend(block(&header));
+            rar.extend(content);
+        }
+        rar.extend(block(&[0x7b, 0x00, 0x40, 7, 0]));
+
+        std::fs::write(path, rar)?;
+        Ok(())
+    }
+
+    fn check_archive(file_name: &str, create: fn(&Path) -> Result<()>) -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join(file_name);
+        create(&path)?;
+
+        assert!(is_supported(&path));
+        let info = inspect(&path)?;
+        assert_eq!(info.pages, PAGES.len() as u32);
+        assert_eq!(info.metadata.series.as_deref(), Some("Series"));
+
+        for (i, (name, content)) in PAGES.into_iter().enumerate() {
+            let page = read_page(&path, i)?;
+            assert_eq!(page.name, name);
+            assert_eq!(page.content, content);
+            assert_eq!(page.format, Some(ImageFormat::Png));
+        }
+        assert!(read_page(&path, PAGES.len()).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_cbz() -> Result<()> {
+        check_archive("chapter.cbz", create_cbz)
+    }
+
+    #[test]
+    fn read_cbz_comment_metadata() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.cbz");
+
+        let mut zip = zip::ZipWriter::new(File::create(&path)?);
+        zip.set_comment(r#"{"ComicBookInfo/1.0": {"series": "Comment Series", "issue": 3}}"#);
+        for (name, content) in PAGES {
+            zip.start_file(name, Default::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+
+        let metadata = inspect(&path)?.metadata;
+        assert_eq!(metadata.series.as_deref(), Some("Comment Series"));
+        assert_eq!(metadata.number.as_deref(), Some("3"));
+
+        // the ComicInfo.xml is preferred over the comment
+        write_metadata(&path, &Metadata::default())?;
+        assert_eq!(inspect(&path)?.metadata, Metadata::default());
+
+        Ok(())
+    }
+
+    #[test]
+    fn invalid_metadata_keeps_pages() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.cbz");
+
+        let mut zip = zip::ZipWriter::new(File::create(&path)?);
+        for (name, content) in PAGES
+            .into_iter()
+            .chain([("ComicInfo.xml", &b"<ComicInfo>"[..])])
+        {
+            zip.start_file(name, Default::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+
+        let info = inspect(&path)?;
+        assert_eq!(info.pages, PAGES.len() as u32);
+        assert_eq!(info.metadata, Metadata::default());
+        let error = info.metadata_error.expect("metadata error");
+        assert!(format!("{error:#}").starts_with("invalid metadata: "));
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_cbz_metadata() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.cbz");
+        create_cbz(&path)?;
+
+        let metadata = Metadata {
+            series: Some("New Series".into()),
+            number: Some("2".into()),
+            ..Default::default()
+        };
+        write_metadata(&path, &metadata)?;
+
+        let info = inspect(&path)?;
+        assert_eq!(info.metadata, metadata);
+        assert_eq!(info.pages, PAGES.len() as u32);
+        assert_eq!(read_page(&path, 0)?.content, PAGES[0].1);
+        // only the chapter is left in the directory
+        assert_eq!(std::fs::read_dir(dir.path())?.count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn fingerprint_follows_content() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("ch1.cbz");
+        create_cbz(&path)?;
+        let original = fingerprint(&path)?;
+
+        let renamed = dir.path().join("Chapter 001.cbz");
+        std::fs::rename(&path, &renamed)?;
+        assert_eq!(fingerprint(&renamed)?, original);
+
+        write_metadata(&renamed, &Metadata::default())?;
+        assert_ne!(fingerprint(&renamed)?, original);
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_cbr() -> Result<()> {
+        check_archive("chapter.cbr", create_cbr)
+    }
+
+    #[test]
+    fn read_cb7() -> Result<()> {
+        check_archive("chapter.cb7", create_cb7)
+    }
+
+    #[test]
+    fn read_cbt() -> Result<()> {
+        check_archive("chapter.CBT", create_cbt)
+    }
+
+    #[test]
+    fn read_folder() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let chapter = dir.path().join("Chapter 1");
+        std::fs::create_dir(&chapter)?;
+        // written in the wrong order to check the sorting
+        let pages: [(&str, &[u8]); 3] = [
+            ("10.jpg", b"\xFF\xD8\xFFten"),
+            ("2.jpg", b"\xFF\xD8\xFFtwo"),
+            ("1.JPG", b"\xFF\xD8\xFFone"),
+        ];
+        for (name, content) in pages {
+            std::fs::write(chapter.join(name), content)?;
+        }
+
+        assert!(is_supported(&chapter));
+        assert_eq!(inspect(&chapter)?.pages, 3);
+        assert_eq!(read_page(&chapter, 0)?.name, "1.JPG");
+        assert_eq!(read_page(&chapter, 2)?.content, b"\xFF\xD8\xFFten");
+        assert!(write_metadata(&chapter, &Metadata::default()).is_err());
+
+        // sidecar files are allowed and the ComicInfo.xml is read
+        std::fs::write(chapter.join("Thumbs.db"), "")?;
+        std::fs::write(
+            chapter.join("ComicInfo.xml"),
+            "<ComicInfo><Series>Series</Series></ComicInfo>",
+        )?;
+        let info = inspect(&chapter)?;
+        assert_eq!(info.pages, 3);
+        assert_eq!(info.metadata.series.as_deref(), Some("Series"));
+
+        // other files don't make it a chapter anymore
+        std::fs::write(chapter.join("notes.txt"), "")?;
+        assert!(!is_supported(&chapter));
+        assert!(!is_supported(dir.path()));
+
+        Ok(())
+    }
+
+    #[test]
+    fn read_epub() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.epub");
+
+        let files: [(&str, &[u8]); 6] = [
+            (
+                "META-INF/container.xml",
+                br#"<?xml version="1.0"?>
+                <container version="1.0" xmlns="urn:oasis:names:tc:opendocument:xmlns:container">
+                    <rootfiles>
+                        <rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/>
+                    </rootfiles>
+                </container>"#,
+            ),
+            (
+                "OEBPS/content.opf",
+                br#"<?xml version="1.0"?>
+                <package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+                    <metadata xmlns:dc="http://purl.org/dc/elements/1.1/">
+                        <dc:title>Chapter One</dc:title>
+                        <dc:creator>Some Author</dc:creator>
+                    </metadata>
+                    <manifest>
+                        <item id="p1" href="text/p1.xhtml" media-type="application/xhtml+xml"/>
+                        <item id="p2" href="images/b.png" media-type="image/png"/>
+                        <item id="i1" href="images/a.png" media-type="image/png"/>
+                    </manifest>
+                    <spine>
+                        <itemref idref="p2"/>
+                        <itemref idref="p1"/>
+                    </spine>
+                </package>"#,
+            ),
+            (
+                "OEBPS/text/p1.xhtml",
+                br#"<?xml version="1.0"?>
+                <html xmlns="http://www.w3.org/1999/xhtml">
+                    <body><img src="../images/a.png"/></body>
+                </html>"#,
+            ),
+            ("OEBPS/images/a.png", PNG),
+            ("OEBPS/images/b.png", b"\x89PNG\r\n\x1a\nb"),
+            ("mimetype", b"application/epub+zip"),
+        ];
+
+        let mut zip = zip::ZipWriter::new(File::create(&path)?);
+        for (name, content) in files {
+            zip.start_file(name, Default::default())?;
+            zip.write_all(content)?;
+        }
+        zip.finish()?;
+
+        let info = inspect(&path)?;
+        assert_eq!(info.pages, 2);
+        assert_eq!(info.metadata.title.as_deref(), Some("Chapter One"));
+        assert_eq!(info.metadata.author.as_deref(), Some("Some Author"));
+
+        // pages follow the spine and not the manifest
+        let page = read_page(&path, 1)?;
+        assert_eq!(page.name, "OEBPS/images/a.png");
+        assert_eq!(page.content, PNG);
+
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_formats() {
+        assert!(!is_supported(Path::new("chapter.mobi")));
+        assert!(!is_supported(Path::new("chapter")));
+        assert!(open("chapter.txt").is_err());
+    }
+}
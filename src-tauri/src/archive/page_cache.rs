@@ -0,0 +1,118 @@
+//! the page names of the recently used chapters, so an archive is only searched for its pages
+//! once, when they are counted during a scan or when the first page is read
+
+use std::{
+    collections::VecDeque,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Arc, LazyLock, Mutex},
+    time::SystemTime,
+};
+
+use anyhow::Result;
+
+use super::ComicArchive;
+
+/// how many chapters the page names are kept for
+const CAPACITY: usize = 64;
+
+/// the page names of a version of a chapter
+type Entry = (Key, Arc<[String]>);
+
+/// the least recently used chapter is first
+static CACHE: LazyLock<Mutex<VecDeque<Entry>>> =
+    LazyLock::new(|| Mutex::new(VecDeque::with_capacity(CAPACITY)));
+
+/// identifies a version of a chapter, a modified chapter gets searched again
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Key {
+    path: PathBuf,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+impl Key {
+    fn new(path: &Path) -> Result<Self> {
+        let meta = fs::metadata(path)?;
+        Ok(Self {
+            path: path.to_owned(),
+            len: meta.len(),
+            modified: meta.modified().ok(),
+        })
+    }
+}
+
+/// the page names of the chapter at the path in reading order, from the cache if possible
+pub fn page_names(path: &Path, archive: &mut dyn ComicArchive) -> Result<Arc<[String]>> {
+    let key = Key::new(path)?;
+    if let Some(names) = get(&key) {
+        return Ok(names);
+    }
+
+    // the cache isn't locked while the archive is read, so other chapters aren't blocked
+    let names: Arc<[String]> = archive.page_names()?.into();
+    insert(key, names.clone());
+    Ok(names)
+}
+
+fn get(key: &Key) -> Option<Arc<[String]>> {
+    let mut cache = CACHE.lock().expect("poisoned page cache");
+    let i = cache.iter().position(|(k, _)| k == key)?;
+    let entry = cache.remove(i)?;
+    let names = entry.1.clone();
+    cache.push_back(entry);
+    Some(names)
+}
+
+fn insert(key: Key, names: Arc<[String]>) {
+    let mut cache = CACHE.lock().expect("poisoned page cache");
+    // other versions of the chapter are outdated
+    cache.retain(|(k, _)| k.path != key.path);
+    if cache.len() >= CAPACITY {
+        cache.pop_front();
+    }
+    cache.push_back((key, names));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// counts how often it's searched for pages
+    struct Counting {
+        searched: usize,
+    }
+
+    impl ComicArchive for Counting {
+        fn file_names(&mut self) -> Result<Vec<String>> {
+            Ok(vec![])
+        }
+
+        fn read_file(&mut self, _: &str) -> Result<Vec<u8>> {
+            Ok(vec![])
+        }
+
+        fn page_names(&mut self) -> Result<Vec<String>> {
+            self.searched += 1;
+            Ok(vec![format!("{}.png", self.searched)])
+        }
+    }
+
+    #[test]
+    fn pages_are_searched_once_per_version() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.cbz");
+        fs::write(&path, "first")?;
+        let mut archive = Counting { searched: 0 };
+
+        assert_eq!(&*page_names(&path, &mut archive)?, ["1.png"]);
+        assert_eq!(&*page_names(&path, &mut archive)?, ["1.png"]);
+        assert_eq!(archive.searched, 1);
+
+        fs::write(&path, "the second version")?;
+        assert_eq!(&*page_names(&path, &mut archive)?, ["2.png"]);
+        assert_eq!(archive.searched, 2);
+
+        Ok(())
+    }
+}
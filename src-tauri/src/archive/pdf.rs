@@ -0,0 +1,268 @@
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    sync::{LazyLock, OnceLock},
+    time::UNIX_EPOCH,
+};
+
+use anyhow::{Context, Result};
+use pdfium_render::prelude::{PdfRenderConfig, Pdfium};
+use sha2::{Digest, Sha256};
+
+use super::ComicArchive;
+use crate::directories::DIRECTORIES;
+
+/// width in pixels the pdf pages get rendered with
+const RENDER_WIDTH: i32 = 1600;
+
+/// file in the cache directory of a pdf with its page count, so it isn't loaded just to count them
+const PAGE_COUNT_FILE: &str = "pages";
+
+/// resource directory of the app, the bundled pdfium is in its `pdfium` directory
+static RESOURCE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// pdfium is only bound once, either from the resources of the app, next to the executable
+/// or from the system
+static PDFIUM: LazyLock<Option<Pdfium>> = LazyLock::new(|| {
+    let exe_dir = std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.to_owned()));
+
+    RESOURCE_DIR
+        .get()
+        .map(|dir| dir.join("pdfium"))
+        .into_iter()
+        .chain(exe_dir)
+        .find_map(|dir| {
+            Pdfium::bind_to_library(Pdfium::pdfium_platform_library_name_at_path(&dir)).ok()
+        })
+        .map_or_else(Pdfium::bind_to_system_library, Ok)
+        .map(Pdfium::new)
+        .ok()
+});
+
+/// where the bundled pdfium can be found, needs to be set before the first pdf is opened
+pub fn set_resource_dir(dir: PathBuf) {
+    // it's only set once at startup, so there's nothing to do when it's set already
+    let _ = RESOURCE_DIR.set(dir);
+}
+
+/// pdf document whose pages get rendered to images
+pub struct Pdf {
+    path: PathBuf,
+    cache_dir: PathBuf,
+}
+
+impl Pdf {
+    pub fn open(path: &Path) -> Result<Self> {
+        Ok(Self {
+            path: path.into(),
+            cache_dir: cache_dir(&DIRECTORIES.pdf_cache_dir(), path)?,
+        })
+    }
+}
+
+impl Pdf {
+    /// create the cache directory of this version of the pdf and remove the older ones
+    fn create_cache_dir(&self) -> Result<()> {
+        if !self.cache_dir.exists() {
+            remove_stale_caches(&self.cache_dir)?;
+            fs::create_dir_all(&self.cache_dir)?;
+        }
+        Ok(())
+    }
+}
+
+impl ComicArchive for Pdf {
+    fn file_names(&mut self) -> Result<Vec<String>> {
+        let count_path = self.cache_dir.join(PAGE_COUNT_FILE);
+        let cached = fs::read_to_string(&count_path)
+            .ok()
+            .and_then(|c| c.parse().ok());
+
+        let count = match cached {
+            Some(count) => count,
+            None => {
+                let pdfium = pdfium()?;
+                let count = pdfium.load_pdf_from_file(&self.path, None)?.pages().len();
+                self.create_cache_dir()?;
+                // written like the pages, so a half written count is never read
+                let mut tmp = tempfile::NamedTempFile::new_in(&self.cache_dir)?;
+                tmp.write_all(count.to_string().as_bytes())?;
+                tmp.persist(&count_path)?;
+                count
+            }
+        };
+
+        Ok((1..=count).map(page_name).collect())
+    }
+
+    /// every page is rendered to an image, so they don't need to be checked
+    fn page_names(&mut self) -> Result<Vec<String>> {
+        self.file_names()
+    }
+
+    fn read_file(&mut self, name: &str) -> Result<Vec<u8>> {
+        let cache_path = self.cache_dir.join(name);
+        if cache_path.exists() {
+            return Ok(fs::read(cache_path)?);
+        }
+
+        let index = page_index(name).with_context(|| format!("invalid page name {name}"))?;
+
+        let pdfium = pdfium()?;
+        let document = pdfium.load_pdf_from_file(&self.path, None)?;
+        let page = document.pages().get(index - 1)?;
+        let image = page
+            .render_with_config(&PdfRenderConfig::new().set_target_width(RENDER_WIDTH))?
+            .as_image();
+
+        self.create_cache_dir()?;
+
+        // render into a temporary file of its own first, so no half written pages can be read,
+        // even when the same page is requested multiple times at once
+        let tmp = tempfile::Builder::new()
+            .suffix(".png")
+            .tempfile_in(&self.cache_dir)?;
+        image.save(tmp.path())?;
+        tmp.persist(&cache_path)?;
+
+        Ok(fs::read(cache_path)?)
+    }
+}
+
+fn pdfium() -> Result<&'static Pdfium> {
+    PDFIUM
+        .as_ref()
+        .context("the pdfium library couldn't be loaded to render pdf files")
+}
+
+fn page_name(page: u16) -> String {
+    format!("{page}.png")
+}
+
+/// the (one based) page number of a page name
+fn page_index(name: &str) -> Option<u16> {
+    name.strip_suffix(".png")?.parse().ok().filter(|&i| i > 0)
+}
+
+/// directory inside of the cache root for the rendered pages of the pdf at the path,
+/// it's named after the path and a version, which changes when the pdf gets modified
+fn cache_dir(root: &Path, path: &Path) -> Result<PathBuf> {
+    let meta = fs::metadata(path)?;
+    let modified = meta.modified()?.duration_since(UNIX_EPOCH)?.as_nanos();
+
+    let mut version = Sha256::new();
+    version.update(meta.len().to_le_bytes());
+    version.update(modified.to_le_bytes());
+
+    Ok(root.join(format!(
+        "{}-{}",
+        short_hash(Sha256::digest(path.to_string_lossy().as_bytes())),
+        short_hash(version.finalize())
+    )))
+}
+
+/// remove the caches of older versions of the same pdf and the ones in an outdated format
+fn remove_stale_caches(cache_dir: &Path) -> Result<()> {
+    let (Some(root), Some(name)) = (cache_dir.parent(), cache_dir.file_name()) else {
+        return Ok(());
+    };
+    let name = name.to_string_lossy();
+    let Some((path_hash, _)) = name.split_once('-') else {
+        return Ok(());
+    };
+    if !root.exists() {
+        return Ok(());
+    }
+
+    for entry in fs::read_dir(root)? {
+        let entry = entry?;
+        let other = entry.file_name().to_string_lossy().into_owned();
+        let is_stale = match other.split_once('-') {
+            Some((other_path_hash, _)) => other_path_hash == path_hash,
+            None => true,
+        };
+
+        if is_stale && entry.file_type()?.is_dir() {
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+
+    Ok(())
+}
+
+fn short_hash(hash: impl AsRef<[u8]>) -> String {
+    hash.as_ref()[..8]
+        .iter()
+        .map(|b| format!("{b:02x}"))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn page_names_are_numbered() {
+        assert_eq!(page_name(12), "12.png");
+        assert_eq!(page_index(&page_name(12)), Some(12));
+        assert_eq!(page_index("0.png"), None);
+        assert_eq!(page_index("1.jpg"), None);
+        assert_eq!(page_index("cover.png"), None);
+    }
+
+    #[test]
+    fn cached_pages_dont_load_the_pdf() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let path = dir.path().join("chapter.pdf");
+        // it's no pdf, so pdfium would fail to load it
+        fs::write(&path, "not a pdf")?;
+
+        let mut pdf = Pdf {
+            cache_dir: cache_dir(dir.path(), &path)?,
+            path,
+        };
+        fs::create_dir_all(&pdf.cache_dir)?;
+        fs::write(pdf.cache_dir.join(PAGE_COUNT_FILE), "2")?;
+        fs::write(pdf.cache_dir.join(page_name(2)), "rendered")?;
+
+        assert_eq!(pdf.page_names()?, ["1.png", "2.png"]);
+        assert_eq!(pdf.read_file("2.png")?, b"rendered");
+
+        Ok(())
+    }
+
+    #[test]
+    fn cache_dir_follows_the_pdf() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let root = dir.path().join("cache");
+        let pdf = dir.path().join("chapter.pdf");
+        let other = dir.path().join("other.pdf");
+        fs::write(&pdf, "first version")?;
+        fs::write(&other, "first version")?;
+
+        let first = cache_dir(&root, &pdf)?;
+        assert_eq!(cache_dir(&root, &pdf)?, first);
+        assert_eq!(first.parent(), Some(root.as_path()));
+        let other_cache = cache_dir(&root, &other)?;
+        assert_ne!(other_cache, first);
+
+        fs::write(&pdf, "the second version")?;
+        let second = cache_dir(&root, &pdf)?;
+        assert_ne!(second, first);
+
+        // the old version and caches of the old format are removed, other pdfs are kept
+        let legacy = root.join("0123456789abcdef");
+        for d in [&first, &other_cache, &legacy] {
+            fs::create_dir_all(d)?;
+        }
+        remove_stale_caches(&second)?;
+        assert!(!first.exists());
+        assert!(!legacy.exists());
+        assert!(other_cache.exists());
+
+        Ok(())
+    }
+}
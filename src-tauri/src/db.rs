@@ -1,19 +1,32 @@
-use std::{path::Path, sync::LazyLock};
+use std::{
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
 
 use rusqlite::params;
 use rusqlite_migration::{Migrations, M};
 use tokio_rusqlite::{Connection, Result};
 
-use crate::entities::{Chapter, ChapterOrdering, Comic};
+use crate::entities::{Chapter, ChapterNumber, ChapterOrdering, Comic, Manga, Metadata, ScanError};
 
 static MIGRATIONS: LazyLock<Migrations<'static>> = LazyLock::new(|| {
     Migrations::new(vec![
         M::up(include_str!("sql/migrations/initial-migration.sql")),
         M::up(include_str!("sql/migrations/1-chapterOrdering.sql")),
+        M::up(include_str!("sql/migrations/2-chapterMetadata.sql")),
+        M::up(include_str!("sql/migrations/3-comicInfo.sql")),
+        M::up(include_str!("sql/migrations/4-scanErrors.sql")),
+        M::up(include_str!("sql/migrations/5-chapterFileStats.sql")),
+        M::up(include_str!("sql/migrations/6-chapterFingerprint.sql")),
+        M::up(include_str!("sql/migrations/7-chapterVolumeGroup.sql")),
+        M::up(include_str!("sql/migrations/8-chapterDisplayNumber.sql")),
+        M::up(include_str!("sql/migrations/9-chapterFileName.sql")),
+        M::up(include_str!("sql/migrations/10-orphanedOrderings.sql")),
     ])
 });
 
-#[derive(Debug)]
+/// the clones share the connection
+#[derive(Debug, Clone)]
 pub struct Database {
     conn: Connection,
 }
@@ -23,16 +36,33 @@ const COMIC_QUERY_ID: &str = include_str!("sql/get_comic.sql");
 const COMIC_INSERT: &str =
     "INSERT INTO comic (dir_path, name, cover_path, is_manga) VALUES (?1, ?2, ?3, ?4)";
 const COMIC_DELETE: &str = "DELETE FROM comic WHERE id = (?1)";
+const COMIC_CHAPTERS_DELETE: &str = "DELETE FROM chapter WHERE comic_id = (?1)";
+const COMIC_ORDERINGS_DELETE: &str = "DELETE FROM chapterordering WHERE comic_id = (?1)";
+const COMIC_MANGA_UPDATE: &str = "UPDATE comic SET is_manga = (?2) WHERE id = (?1)";
+const COMIC_MOVE: &str = "UPDATE comic SET dir_path = (?2), name = (?3) WHERE id = (?1)";
 
 const CHAPTER_QUERY: &str =
-    "SELECT id, file_path, chapter_number, read, pages, comic_id, name FROM chapter WHERE comic_id = (?1) ORDER BY chapter_number";
+    "SELECT id, file_path, chapter_number, read, pages, comic_id, name, title, author, series, number, volume, penciller, summary, genre, language, manga, broken, file_size, modified, fingerprint, volume_group, display_volume, display_number, display_suffix, is_extra, name_title, scanlation_group, name_language FROM chapter WHERE comic_id = (?1) ORDER BY chapter_number";
+const CHAPTER_QUERY_ALL: &str =
+    "SELECT id, file_path, chapter_number, read, pages, comic_id, name, title, author, series, number, volume, penciller, summary, genre, language, manga, broken, file_size, modified, fingerprint, volume_group, display_volume, display_number, display_suffix, is_extra, name_title, scanlation_group, name_language FROM chapter ORDER BY comic_id, chapter_number";
+const CHAPTER_QUERY_ID: &str =
+    "SELECT id, file_path, chapter_number, read, pages, comic_id, name, title, author, series, number, volume, penciller, summary, genre, language, manga, broken, file_size, modified, fingerprint, volume_group, display_volume, display_number, display_suffix, is_extra, name_title, scanlation_group, name_language FROM chapter WHERE id = (?1)";
 const CHAPTER_DELETE: &str = "DELETE FROM chapter WHERE id = (?1)";
 const CHAPTER_ORDER_QUERY: &str =
-    "SELECT id, file_path, chapter_number, read, pages, comic_id, name FROM chapter WHERE comic_id = (?1) AND chapter_number = (?2)";
+    "SELECT id, file_path, chapter_number, read, pages, comic_id, name, title, author, series, number, volume, penciller, summary, genre, language, manga, broken, file_size, modified, fingerprint, volume_group, display_volume, display_number, display_suffix, is_extra, name_title, scanlation_group, name_language FROM chapter WHERE comic_id = (?1) AND chapter_number = (?2)";
 // const CHAPTER_INSERT: &str =
 //     "INSERT INTO chapter (file_path, chapter_number, read, pages, comic_id, name) VALUES (?1, ?2, ?3, ?4, ?5, ?6)";
 const CHAPTER_UPSERT: &str = include_str!("sql/upsert_chapter.sql");
+const CHAPTER_MOVE: &str = "UPDATE chapter SET file_path = (?2), comic_id = (?3) WHERE id = (?1)";
+const CHAPTER_NUMBER_UPDATE: &str = "UPDATE chapter SET chapter_number = (?2) WHERE id = (?1)";
 const CHAPTER_PAGE_UPDATE: &str = "UPDATE chapter SET read = (?2) WHERE id = (?1)";
+const CHAPTER_METADATA_UPDATE: &str = include_str!("sql/update_chapter_metadata.sql");
+
+const SCAN_ERROR_QUERY: &str = "SELECT path, message FROM scanerror ORDER BY path";
+const SCAN_ERROR_INSERT: &str = "INSERT OR REPLACE INTO scanerror (path, message) VALUES (?1, ?2)";
+const SCAN_ERROR_DELETE_ALL: &str = "DELETE FROM scanerror";
+const SCAN_ERROR_DELETE_DIR: &str =
+    "DELETE FROM scanerror WHERE path = (?1) OR substr(path, 1, length(?2)) = (?2)";
 
 const CHAPTER_ORDERING_QUERY: &str =
     "SELECT id, comic_id, rank, regex FROM chapterordering WHERE comic_id = (?1) ORDER BY rank";
@@ -102,6 +132,23 @@ impl Database {
             .await
     }
 
+    /// the chapters of every comic, so a scan doesn't need a query per comic
+    pub async fn chapters(&self) -> Result<Vec<Chapter>> {
+        self.conn
+            .call(|c| {
+                c.prepare(CHAPTER_QUERY_ALL)?
+                    .query_map([], chapter_from_row)?
+                    .try_collect::<Vec<_>>()
+            })
+            .await
+    }
+
+    pub async fn chapter(&self, chapter_id: u32) -> Result<Chapter> {
+        self.conn
+            .call(move |c| c.query_row(CHAPTER_QUERY_ID, [chapter_id], chapter_from_row))
+            .await
+    }
+
     pub async fn chapter_by_number(&self, comic_id: u32, chapter_number: u32) -> Result<Chapter> {
         self.conn
             .call(move |c| {
@@ -132,6 +179,49 @@ impl Database {
             .await
     }
 
+    pub async fn scan_errors(&self) -> Result<Vec<ScanError>> {
+        self.conn
+            .call(|c| {
+                c.prepare(SCAN_ERROR_QUERY)?
+                    .query_map([], scan_error_from_row)?
+                    .try_collect()
+            })
+            .await
+    }
+
+    /// replace the errors of the last scan of the directories (or the whole library) with the new ones
+    pub async fn replace_scan_errors(
+        &mut self,
+        dirs: Option<Vec<PathBuf>>,
+        errors: Vec<ScanError>,
+    ) -> Result<()> {
+        self.conn
+            .call(move |c| {
+                let tx = c.transaction()?;
+                match dirs {
+                    Some(dirs) => {
+                        for dir in dirs {
+                            let dir = dir.to_string_lossy();
+                            let prefix = format!("{dir}{}", std::path::MAIN_SEPARATOR);
+                            tx.execute(SCAN_ERROR_DELETE_DIR, params![dir, prefix])?;
+                        }
+                    }
+                    None => {
+                        tx.execute(SCAN_ERROR_DELETE_ALL, [])?;
+                    }
+                };
+
+                let mut insert = tx.prepare(SCAN_ERROR_INSERT)?;
+                for e in errors {
+                    insert.execute(params![e.path.to_string_lossy(), e.message])?;
+                }
+                drop(insert);
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+
     pub async fn update_chapter_page(&mut self, chapter_id: u32, page: u32) -> Result<()> {
         self.conn
             .call(move |c| {
@@ -141,20 +231,101 @@ impl Database {
             .await
     }
 
-    pub async fn update_chapter_ordering(&mut self, o: ChapterOrdering) -> Result<()> {
+    /// store the metadata of the chapter with the file stats and fingerprint of its archive
+    pub async fn update_chapter_metadata(&mut self, chapter: Chapter) -> Result<()> {
+        self.conn
+            .call(move |c| {
+                let m = chapter.metadata;
+                c.execute(
+                    CHAPTER_METADATA_UPDATE,
+                    params![
+                        chapter.id,
+                        m.title,
+                        m.author,
+                        m.series,
+                        m.number,
+                        m.volume,
+                        m.penciller,
+                        m.summary,
+                        m.genre,
+                        m.language,
+                        m.manga.map(Manga::as_str),
+                        chapter.file_size,
+                        chapter.modified,
+                        chapter.fingerprint,
+                    ],
+                )
+                .map(|_| ())
+            })
+            .await
+    }
+
+    pub async fn update_comics_manga(&mut self, comics: Vec<(u32, bool)>) -> Result<()> {
         self.conn
             .call(move |c| {
-                c.execute(CHAPTER_ORDER_UPDATE, params![o.id, o.regex, o.rank])
+                let tx = c.transaction()?;
+                let mut update = tx.prepare(COMIC_MANGA_UPDATE)?;
+
+                for (id, is_manga) in comics {
+                    update.execute(params![id, is_manga])?;
+                }
+                drop(update);
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// give a renamed comic its new directory, it keeps its id and with it the chapter orderings
+    pub async fn move_comic(&mut self, id: u32, dir_path: PathBuf, name: String) -> Result<()> {
+        self.conn
+            .call(move |c| {
+                c.execute(COMIC_MOVE, params![id, dir_path.to_string_lossy(), name])
                     .map(|_| ())
             })
             .await
     }
 
-    pub async fn insert_comics(&mut self, comics: Vec<Comic>) -> Result<()> {
+    /// store the new path (and comic) of the chapters, which are identified by their id
+    pub async fn move_chapters(&mut self, chapters: Vec<Chapter>) -> Result<()> {
+        self.conn
+            .call(move |c| {
+                let tx = c.transaction()?;
+                let mut update = tx.prepare(CHAPTER_MOVE)?;
+
+                for c in chapters {
+                    update.execute(params![c.id, c.path.to_string_lossy(), c.comic_id])?;
+                }
+                drop(update);
+                tx.commit()?;
+                Ok(())
+            })
+            .await
+    }
+
+    /// change a chapter ordering and give the chapters of its comic their new numbers
+    pub async fn update_chapter_ordering(
+        &mut self,
+        o: ChapterOrdering,
+        chapters: Vec<Chapter>,
+    ) -> Result<()> {
+        self.conn
+            .call(move |c| {
+                let tx = c.transaction()?;
+                tx.execute(CHAPTER_ORDER_UPDATE, params![o.id, o.regex, o.rank])?;
+                renumber_chapters(&tx, &chapters)?;
+                tx.commit()
+            })
+            .await
+    }
+
+    /// insert the comics with their chapters and return the ids of the comics
+    pub async fn insert_comics(&mut self, comics: Vec<Comic>) -> Result<Vec<u32>> {
         self.conn
             .call(|c| {
                 let tx = c.transaction()?;
                 let mut insert = tx.prepare(COMIC_INSERT)?;
+                let mut ids = vec![];
 
                 for c in comics {
                     let id = insert.insert(params![
@@ -169,22 +340,15 @@ impl Database {
                     let mut insert = tx.prepare(CHAPTER_UPSERT)?;
 
                     for c in c.chapters {
-                        let comic = id;
-                        insert.insert(params![
-                            c.path.to_string_lossy(),
-                            c.chapter_number,
-                            c.read,
-                            c.pages,
-                            comic,
-                            c.name,
-                        ])?;
+                        upsert_chapter(&mut insert, &c, id as u32)?;
                     }
+                    ids.push(id as u32);
                 }
 
                 drop(insert);
                 tx.commit()?;
 
-                Ok(())
+                Ok(ids)
             })
             .await
     }
@@ -196,15 +360,7 @@ impl Database {
                 let mut insert = tx.prepare(CHAPTER_UPSERT)?;
 
                 for c in chapters {
-                    let comic = c.comic_id;
-                    insert.insert(params![
-                        c.path.to_string_lossy(),
-                        c.chapter_number,
-                        c.read,
-                        c.pages,
-                        comic,
-                        c.name,
-                    ])?;
+                    upsert_chapter(&mut insert, &c, c.comic_id)?;
                 }
                 drop(insert);
                 tx.commit()?;
@@ -235,27 +391,38 @@ impl Database {
     //     Ok(())
     // }
 
-    pub async fn insert_chapter_ordering(&mut self, ordering: ChapterOrdering) -> Result<()> {
+    /// insert a chapter ordering and give the chapters of its comic their new numbers
+    pub async fn insert_chapter_ordering(
+        &mut self,
+        ordering: ChapterOrdering,
+        chapters: Vec<Chapter>,
+    ) -> Result<()> {
         self.conn
             .call(move |c| {
-                c.execute(
+                let tx = c.transaction()?;
+                tx.execute(
                     CHAPTER_ORDER_INSERT,
                     params![ordering.comic_id, ordering.rank, ordering.regex],
-                )
-                .map(|_| ())
+                )?;
+                renumber_chapters(&tx, &chapters)?;
+                tx.commit()
             })
             .await
     }
 
-    pub async fn delete_chapter_ordering(&mut self, id: u32) -> Result<()> {
-        let order = self.chapter_ordering(id).await?;
-
+    /// delete a chapter ordering and give the chapters of its comic their new numbers
+    pub async fn delete_chapter_ordering(
+        &mut self,
+        order: ChapterOrdering,
+        chapters: Vec<Chapter>,
+    ) -> Result<()> {
         self.conn
             .call(move |c| {
-                c.execute(CHAPTER_ORDER_DELETE, params![id])?;
-
-                c.execute(CHAPTER_ORDER_DECREMENT, params![order.comic_id, order.rank])
-                    .map(|_| ())
+                let tx = c.transaction()?;
+                tx.execute(CHAPTER_ORDER_DELETE, params![order.id])?;
+                tx.execute(CHAPTER_ORDER_DECREMENT, params![order.comic_id, order.rank])?;
+                renumber_chapters(&tx, &chapters)?;
+                tx.commit()
             })
             .await
     }
@@ -281,17 +448,74 @@ impl Database {
             .call(move |c| {
                 let tx = c.transaction()?;
                 let mut delete = tx.prepare(COMIC_DELETE)?;
+                let mut delete_chapters = tx.prepare(COMIC_CHAPTERS_DELETE)?;
+                let mut delete_orderings = tx.prepare(COMIC_ORDERINGS_DELETE)?;
 
                 for c in comics {
-                    delete.insert(params![c.id])?;
+                    delete.execute(params![c.id])?;
+                    // foreign keys aren't enforced, so the chapters and orderings
+                    // need to be removed by hand
+                    delete_chapters.execute(params![c.id])?;
+                    delete_orderings.execute(params![c.id])?;
                 }
                 drop(delete);
+                drop(delete_chapters);
+                drop(delete_orderings);
                 tx.commit()?;
                 Ok(())
             })
             .await
     }
 }
+
+fn renumber_chapters(tx: &rusqlite::Transaction, chapters: &[Chapter]) -> rusqlite::Result<()> {
+    let mut update = tx.prepare(CHAPTER_NUMBER_UPDATE)?;
+    for c in chapters {
+        update.execute(params![c.id, c.chapter_number])?;
+    }
+
+    Ok(())
+}
+
+fn upsert_chapter(
+    insert: &mut rusqlite::Statement,
+    c: &Chapter,
+    comic_id: u32,
+) -> rusqlite::Result<i64> {
+    let m = &c.metadata;
+    let number = c.display_number.clone().unwrap_or_default();
+    insert.insert(params![
+        c.path.to_string_lossy(),
+        c.chapter_number,
+        c.read,
+        c.pages,
+        comic_id,
+        c.name,
+        m.title,
+        m.author,
+        m.series,
+        m.number,
+        m.volume,
+        m.penciller,
+        m.summary,
+        m.genre,
+        m.language,
+        m.manga.map(Manga::as_str),
+        c.broken,
+        c.file_size,
+        c.modified,
+        c.fingerprint,
+        c.volume_group,
+        number.volume,
+        number.number,
+        number.suffix,
+        number.extra,
+        c.name_title,
+        c.scanlation_group,
+        c.name_language,
+    ])
+}
+
 fn comic_from_row(r: &rusqlite::Row) -> rusqlite::Result<Comic> {
     Ok(Comic {
         id: r.get(0)?,
@@ -301,6 +525,17 @@ fn comic_from_row(r: &rusqlite::Row) -> rusqlite::Result<Comic> {
         is_manga: r.get(4)?,
         chapter_count: r.get(5).ok(),
         chapter_read: r.get(6).ok(),
+        // only the fields which apply to the whole comic
+        metadata: Metadata {
+            series: r.get(7)?,
+            author: r.get(8)?,
+            penciller: r.get(9)?,
+            summary: r.get(10)?,
+            genre: r.get(11)?,
+            language: r.get(12)?,
+            manga: manga_from_sql(r.get(13)?),
+            ..Default::default()
+        },
         chapters: vec![],
     })
 }
@@ -314,6 +549,49 @@ fn chapter_from_row(r: &rusqlite::Row) -> rusqlite::Result<Chapter> {
         pages: r.get(4)?,
         comic_id: r.get(5)?,
         name: r.get(6)?,
+        metadata: Metadata {
+            title: r.get(7)?,
+            author: r.get(8)?,
+            series: r.get(9)?,
+            number: r.get(10)?,
+            volume: r.get(11)?,
+            penciller: r.get(12)?,
+            summary: r.get(13)?,
+            genre: r.get(14)?,
+            language: r.get(15)?,
+            manga: manga_from_sql(r.get(16)?),
+        },
+        broken: r.get(17)?,
+        file_size: r.get(18)?,
+        modified: r.get(19)?,
+        fingerprint: r.get(20)?,
+        volume_group: r.get(21)?,
+        display_number: chapter_number_from_row(r)?,
+        name_title: r.get(26)?,
+        scanlation_group: r.get(27)?,
+        name_language: r.get(28)?,
+    })
+}
+
+fn chapter_number_from_row(r: &rusqlite::Row) -> rusqlite::Result<Option<ChapterNumber>> {
+    let number = ChapterNumber {
+        volume: r.get(22)?,
+        number: r.get(23)?,
+        suffix: r.get(24)?,
+        extra: r.get(25)?,
+    };
+
+    Ok((number != ChapterNumber::default()).then_some(number))
+}
+
+fn manga_from_sql(manga: Option<String>) -> Option<Manga> {
+    manga.as_deref().and_then(Manga::parse)
+}
+
+fn scan_error_from_row(r: &rusqlite::Row) -> rusqlite::Result<ScanError> {
+    Ok(ScanError {
+        path: r.get::<_, String>(0)?.into(),
+        message: r.get(1)?,
     })
 }
 
@@ -341,6 +619,15 @@ mod tests {
         let conn = Connection::open_in_memory().await?;
         let mut db = Database::_from_conn(conn).await?;
 
+        let berserk_meta = Metadata {
+            series: Some("Berserk".to_string()),
+            number: Some("3".to_string()),
+            title: Some("The Guardians of Desire".to_string()),
+            author: Some("Kentaro Miura".to_string()),
+            manga: Some(Manga::YesAndRightToLeft),
+            ..Default::default()
+        };
+
         let comics = vec![
             Comic {
                 id: 0,
@@ -350,6 +637,7 @@ mod tests {
                 is_manga: true,
                 chapter_count: None,
                 chapter_read: None,
+                metadata: Metadata::default(),
                 chapters: vec![
                     Chapter {
                         id: 0,
@@ -359,6 +647,16 @@ mod tests {
                         read: 30,
                         pages: 30,
                         chapter_number: 1,
+                        metadata: Metadata::default(),
+                        broken: false,
+                        file_size: 0,
+                        modified: 0,
+                        fingerprint: None,
+                        volume_group: None,
+                        name_title: None,
+                        scanlation_group: None,
+                        name_language: None,
+                        display_number: None,
                     },
                     Chapter {
                         id: 0,
@@ -368,6 +666,16 @@ mod tests {
                         read: 12,
                         pages: 23,
                         chapter_number: 2,
+                        metadata: Metadata::default(),
+                        broken: false,
+                        file_size: 0,
+                        modified: 0,
+                        fingerprint: None,
+                        volume_group: None,
+                        name_title: None,
+                        scanlation_group: None,
+                        name_language: None,
+                        display_number: None,
                     },
                 ],
             },
@@ -379,6 +687,7 @@ mod tests {
                 is_manga: true,
                 chapter_count: None,
                 chapter_read: None,
+                metadata: Metadata::default(),
                 chapters: vec![
                     Chapter {
                         id: 0,
@@ -388,6 +697,16 @@ mod tests {
                         read: 67,
                         pages: 97,
                         chapter_number: 1,
+                        metadata: Metadata::default(),
+                        broken: false,
+                        file_size: 0,
+                        modified: 0,
+                        fingerprint: None,
+                        volume_group: None,
+                        name_title: None,
+                        scanlation_group: None,
+                        name_language: None,
+                        display_number: None,
                     },
                     Chapter {
                         id: 0,
@@ -397,16 +716,60 @@ mod tests {
                         read: 0,
                         pages: 54,
                         chapter_number: 2,
+                        metadata: berserk_meta.clone(),
+                        broken: false,
+                        file_size: 0,
+                        modified: 0,
+                        fingerprint: None,
+                        volume_group: None,
+                        name_title: None,
+                        scanlation_group: None,
+                        name_language: None,
+                        display_number: None,
                     },
                 ],
             },
         ];
 
-        db.insert_comics(comics).await?;
+        assert_eq!(db.insert_comics(comics).await?, vec![1, 2]);
 
         assert_eq!(db.comics().await?.len(), 2);
         assert_eq!(db.comic_with_chapters(2).await?.chapters.len(), 2);
 
+        let berserk = db.comic_with_chapters(2).await?;
+        assert_eq!(berserk.chapters[1].metadata, berserk_meta);
+        // the comic only gets the fields which aren't chapter specific
+        assert_eq!(berserk.metadata.series, berserk_meta.series);
+        assert_eq!(berserk.metadata.manga, berserk_meta.manga);
+        assert_eq!(berserk.metadata.title, None);
+        assert_eq!(db.comic(1).await?.metadata, Metadata::default());
+
+        let edited = Metadata {
+            title: Some("Lost Children".to_string()),
+            ..berserk_meta
+        };
+        let chapter = Chapter {
+            metadata: edited.clone(),
+            ..berserk.chapters[1].clone()
+        };
+        db.update_chapter_metadata(chapter.clone()).await?;
+        assert_eq!(db.chapter(chapter.id).await?, chapter);
+
+        // the orderings are deleted with their comic
+        db.insert_chapter_ordering(
+            ChapterOrdering {
+                id: 0,
+                comic_id: berserk.id,
+                rank: 1,
+                regex: "Lost".into(),
+            },
+            vec![],
+        )
+        .await?;
+        db.delete_comics(vec![berserk.clone()]).await?;
+        assert!(db.chapter_orderings(berserk.id).await?.is_empty());
+        assert_eq!(db.comics().await?.len(), 1);
+
         Ok(())
     }
 }
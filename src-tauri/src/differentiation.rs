@@ -1,3 +1,8 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    hash::Hash,
+};
+
 #[derive(Clone, Debug)]
 pub struct DifferentiationResult<T> {
     pub added: Vec<T>,
@@ -5,43 +10,104 @@ pub struct DifferentiationResult<T> {
     pub kept: Vec<(T, T)>,
 }
 
-fn _differentiate<T: PartialEq>(old: Vec<T>, new: Vec<T>) -> DifferentiationResult<T> {
+fn _differentiate<T: Hash + Eq>(old: Vec<T>, new: Vec<T>) -> DifferentiationResult<T> {
     differentiate_on(old, new, |x| x)
 }
 
-pub fn differentiate_on<T, U, F>(
-    mut old: Vec<T>,
-    mut new: Vec<T>,
-    on: F,
-) -> DifferentiationResult<T>
+/// split the elements into the added, deleted and kept (as pairs of old and new) ones,
+/// they are matched by the key returned by `on` and stay in the order they were passed in
+pub fn differentiate_on<T, U, F>(old: Vec<T>, new: Vec<T>, on: F) -> DifferentiationResult<T>
 where
     F: Fn(&T) -> &U,
-    U: PartialEq,
+    U: Hash + Eq,
 {
-    let len = old.len();
-    let mut keep = Vec::new();
+    // the index of the matching new element for every old one,
+    // elements with the same key are matched in order
+    let matches = {
+        let mut positions: HashMap<&U, VecDeque<usize>> = HashMap::with_capacity(new.len());
+        for (i, n) in new.iter().enumerate() {
+            positions.entry(on(n)).or_default().push_back(i);
+        }
 
-    for i in 0..len {
-        let i = i - keep.len();
+        old.iter()
+            .map(|o| positions.get_mut(on(o)).and_then(VecDeque::pop_front))
+            .collect::<Vec<_>>()
+    };
 
-        if let Some(idx) = new.iter().position(|n| on(&old[i]) == on(n)) {
-            let o = old.swap_remove(i);
-            let n = new.swap_remove(idx);
+    let mut new = new.into_iter().map(Some).collect::<Vec<_>>();
+    let mut deleted = Vec::new();
+    let mut kept = Vec::new();
 
-            keep.push((o, n));
+    for (o, idx) in old.into_iter().zip(matches) {
+        match idx {
+            Some(idx) => kept.push((o, new[idx].take().expect("matched twice"))),
+            None => deleted.push(o),
         }
     }
 
     DifferentiationResult {
-        added: new,
-        deleted: old,
-        kept: keep,
+        added: new.into_iter().flatten().collect(),
+        deleted,
+        kept,
     }
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
+    use proptest::{
+        collection::{hash_set, vec},
+        prelude::*,
+    };
+
+    /// the previous implementation with a linear search, the hash based one is checked against it
+    fn linear_differentiate_on<T, U, F>(
+        mut old: Vec<T>,
+        mut new: Vec<T>,
+        on: F,
+    ) -> DifferentiationResult<T>
+    where
+        F: Fn(&T) -> &U,
+        U: PartialEq,
+    {
+        let len = old.len();
+        let mut keep = Vec::new();
+
+        for i in 0..len {
+            let i = i - keep.len();
+
+            if let Some(idx) = new.iter().position(|n| on(&old[i]) == on(n)) {
+                let o = old.swap_remove(i);
+                let n = new.swap_remove(idx);
+
+                keep.push((o, n));
+            }
+        }
+
+        DifferentiationResult {
+            added: new,
+            deleted: old,
+            kept: keep,
+        }
+    }
+
+    /// elements with a small key range, so there are a lot of matches and duplicates
+    type Entry = (u8, u16);
+
+    fn key(e: &Entry) -> &u8 {
+        &e.0
+    }
+
+    fn sorted<T: Ord>(mut v: Vec<T>) -> Vec<T> {
+        v.sort();
+        v
+    }
+
+    fn is_subsequence<T: PartialEq>(sub: &[T], seq: &[T]) -> bool {
+        let mut seq = seq.iter();
+        sub.iter().all(|s| seq.any(|e| e == s))
+    }
+
     #[test]
     fn diff_test() {
         let a = vec![1, 2, 3, 4, 5, 6, 7, 8, 9, 0];
@@ -54,4 +120,50 @@ mod test {
         assert_eq!(result.deleted, [1, 4, 6, 9]);
         //assert_eq!(result.kept, [2, 3, 5, 7, 8, 0]);
     }
+
+    proptest! {
+        #[test]
+        fn same_as_linear(old in vec((0u8..16, any::<u16>()), 0..64),
+                          new in vec((0u8..16, any::<u16>()), 0..64)) {
+            let hashed = differentiate_on(old.clone(), new.clone(), key);
+            let linear = linear_differentiate_on(old.clone(), new.clone(), key);
+
+            // with duplicate keys other elements can be paired, but the keys are the same
+            let keys = |v: &[Entry]| sorted(v.iter().map(|e| e.0).collect());
+            let kept_keys = |v: &[(Entry, Entry)]| sorted(v.iter().map(|(o, _)| o.0).collect());
+            prop_assert_eq!(keys(&hashed.added), keys(&linear.added));
+            prop_assert_eq!(keys(&hashed.deleted), keys(&linear.deleted));
+            prop_assert_eq!(kept_keys(&hashed.kept), kept_keys(&linear.kept));
+            prop_assert!(hashed.kept.iter().all(|(o, n)| o.0 == n.0));
+
+            // every element ends up exactly once in the result
+            let (kept_old, kept_new): (Vec<_>, Vec<_>) = hashed.kept.iter().copied().unzip();
+            prop_assert_eq!(
+                sorted([hashed.deleted.clone(), kept_old.clone()].concat()),
+                sorted(old.clone())
+            );
+            prop_assert_eq!(
+                sorted([hashed.added.clone(), kept_new].concat()),
+                sorted(new.clone())
+            );
+
+            // the order of the input is kept, the kept pairs follow the old elements
+            prop_assert!(is_subsequence(&hashed.deleted, &old));
+            prop_assert!(is_subsequence(&kept_old, &old));
+            prop_assert!(is_subsequence(&hashed.added, &new));
+        }
+
+        #[test]
+        fn same_as_linear_with_unique_keys(old in hash_set(any::<u16>(), 0..64),
+                                           new in hash_set(any::<u16>(), 0..64)) {
+            let old = old.into_iter().collect::<Vec<_>>();
+            let new = new.into_iter().collect::<Vec<_>>();
+            let hashed = _differentiate(old.clone(), new.clone());
+            let linear = linear_differentiate_on(old, new, |x| x);
+
+            prop_assert_eq!(sorted(hashed.added), sorted(linear.added));
+            prop_assert_eq!(sorted(hashed.deleted), sorted(linear.deleted));
+            prop_assert_eq!(sorted(hashed.kept), sorted(linear.kept));
+        }
+    }
 }
@@ -16,6 +16,10 @@ impl ComicProjDirs {
     pub fn config_file_path(&self) -> PathBuf {
         self.config_dir().join("config.json")
     }
+
+    pub fn pdf_cache_dir(&self) -> PathBuf {
+        self.cache_dir().join("pdf")
+    }
 }
 
 impl Deref for ComicProjDirs {
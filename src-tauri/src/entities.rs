@@ -11,26 +11,150 @@ pub struct Comic {
     pub name: String,
     pub cover_path: Option<PathBuf>,
     pub is_manga: bool,
+    /// metadata of the first chapter which has some, without the chapter specific fields
+    pub metadata: Metadata,
 
     pub chapters: Vec<Chapter>,
     pub chapter_count: Option<usize>,
     pub chapter_read: Option<usize>,
 }
 
-#[derive(Debug, Clone, Serialize, TS)]
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
 #[ts(export, export_to = "../src/entities/")]
 pub struct Chapter {
     pub id: u32,
     pub path: PathBuf,
     pub name: String,
+    /// position of the chapter inside of the comic, starting at 1
     pub chapter_number: u32,
+    /// the number the chapter really has, parsed from its name or `ComicInfo.xml`
+    pub display_number: Option<ChapterNumber>,
+    /// the directory (e.g. a volume or arc) between the comic and the chapter,
+    /// `None` when the chapter is directly inside of the comic
+    pub volume_group: Option<String>,
+    /// the title, scanlation group and language tag (e.g. `[en]`) from the chapter name
+    pub name_title: Option<String>,
+    pub scanlation_group: Option<String>,
+    pub name_language: Option<String>,
+    pub metadata: Metadata,
 
     pub read: u32,
     pub pages: u32,
+    /// the archive couldn't be read, see the scan report for the reason
+    pub broken: bool,
+    /// size and modification time (unix millis) of the archive when it was read,
+    /// so unchanged archives are skipped by a rescan
+    #[serde(skip)]
+    pub file_size: u64,
+    #[serde(skip)]
+    pub modified: i64,
+    /// hash of the archive content, to recognize it after it was renamed or moved
+    #[serde(skip)]
+    pub fingerprint: Option<String>,
 
     pub comic_id: u32,
 }
 
+/// the chapter number as it's written in the chapter name (e.g. `Vol.2 Ch.10.5a` or `Extra 3`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub struct ChapterNumber {
+    pub volume: Option<u32>,
+    pub number: Option<f64>,
+    /// letters right after the number, e.g. the `a` of `12a`
+    pub suffix: Option<String>,
+    /// extras, specials or omakes, which come after the regular chapters
+    pub extra: bool,
+}
+
+/// information about a chapter, which is stored inside of its archive
+/// (e.g. in a `ComicInfo.xml`)
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub struct Metadata {
+    pub series: Option<String>,
+    pub number: Option<String>,
+    pub volume: Option<i32>,
+    pub title: Option<String>,
+    /// the writer of the comic
+    pub author: Option<String>,
+    pub penciller: Option<String>,
+    pub summary: Option<String>,
+    pub genre: Option<String>,
+    pub language: Option<String>,
+    pub manga: Option<Manga>,
+}
+
+/// the reading direction stored in the `Manga` field of the `ComicInfo.xml`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub enum Manga {
+    Unknown,
+    No,
+    Yes,
+    YesAndRightToLeft,
+}
+
+impl Manga {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Unknown => "Unknown",
+            Self::No => "No",
+            Self::Yes => "Yes",
+            Self::YesAndRightToLeft => "YesAndRightToLeft",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "Unknown" => Some(Self::Unknown),
+            "No" => Some(Self::No),
+            "Yes" => Some(Self::Yes),
+            "YesAndRightToLeft" => Some(Self::YesAndRightToLeft),
+            _ => None,
+        }
+    }
+}
+
+/// comics which were changed by a scan, it's sent with the `comics_reloaded` event
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub struct LibraryChanges {
+    pub added_comics: Vec<u32>,
+    pub updated_comics: Vec<u32>,
+    pub deleted_comics: Vec<u32>,
+}
+
+impl LibraryChanges {
+    pub fn is_empty(&self) -> bool {
+        self.added_comics.is_empty()
+            && self.updated_comics.is_empty()
+            && self.deleted_comics.is_empty()
+    }
+}
+
+/// problem with a file or directory, which was found while scanning the library
+#[derive(Debug, Clone, PartialEq, Serialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub struct ScanError {
+    pub path: PathBuf,
+    pub message: String,
+}
+
+/// progress of a running library scan, it's sent with the `scan_progress` event
+#[derive(Debug, Clone, Default, Serialize, TS)]
+#[ts(export, export_to = "../src/entities/")]
+pub struct ScanProgress {
+    pub comics_discovered: u32,
+    pub comics_processed: u32,
+    pub chapters_processed: u32,
+    /// chapter which is read at the moment
+    pub current_file: Option<PathBuf>,
+    pub errors: u32,
+    pub finished: bool,
+    pub cancelled: bool,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export, export_to = "../src/entities/")]
 pub struct ChapterOrdering {
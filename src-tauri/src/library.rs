@@ -1,16 +1,29 @@
 use std::{
+    borrow::Cow,
     collections::HashMap,
-    fs::File,
     path::{Path, PathBuf},
-    sync::LazyLock,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, LazyLock, Mutex,
+    },
+    time::UNIX_EPOCH,
 };
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
 use itertools::Itertools;
-use tokio::fs::DirEntry;
+use notify_debouncer_mini::{notify::RecommendedWatcher, Debouncer};
+use tokio::{
+    fs::DirEntry,
+    sync::{oneshot, watch, Mutex as AsyncMutex, OwnedMutexGuard},
+};
 use tokio_stream::{Stream, StreamExt};
 
-use crate::entities::{Chapter, Comic};
+use crate::archive;
+use crate::entities::{
+    Chapter, ChapterNumber, ChapterOrdering, Comic, LibraryChanges, Manga, Metadata, ScanError,
+    ScanProgress,
+};
+use crate::metadata::{chapter_number, file_name};
 use crate::{db::Database, differentiation::differentiate_on};
 
 #[derive(Debug)]
@@ -18,80 +31,650 @@ pub struct Library {
     pub database: Database,
     pub path: PathBuf,
     pub is_manga_db: bool,
+    /// also write metadata edits into the `ComicInfo.xml` of the chapters
+    pub write_comic_info: bool,
+    /// keeps the library up to date while it's loaded, see [`crate::watcher`]
+    pub watcher: Option<Debouncer<RecommendedWatcher>>,
+    /// the periodic rescan stops when this is dropped
+    pub rescan_timer: Option<oneshot::Sender<()>>,
+    /// progress and cancellation of the running scan
+    pub scan: ScanHandle,
+}
+
+/// follows the progress of the library scans and can cancel them,
+/// it's shared with the frontend, so the running scan can be cancelled without the library
+#[derive(Debug, Clone)]
+pub struct ScanHandle {
+    cancelled: Arc<AtomicBool>,
+    progress: Arc<watch::Sender<ScanProgress>>,
+    errors: Arc<Mutex<Vec<ScanError>>>,
+    /// held by the running scan, so the scans don't overlap
+    running: Arc<AsyncMutex<()>>,
+}
+
+impl Default for ScanHandle {
+    fn default() -> Self {
+        Self {
+            cancelled: Default::default(),
+            progress: Arc::new(watch::channel(ScanProgress::default()).0),
+            errors: Default::default(),
+            running: Default::default(),
+        }
+    }
+}
+
+impl ScanHandle {
+    /// stop the running scan, the comics which have been completely scanned until then are kept
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn subscribe(&self) -> watch::Receiver<ScanProgress> {
+        self.progress.subscribe()
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// wait for the running scan to end and reset the progress,
+    /// other scans wait until the returned guard is dropped
+    async fn start(&self) -> OwnedMutexGuard<()> {
+        let running = self.running.clone().lock_owned().await;
+        self.cancelled.store(false, Ordering::Relaxed);
+        self.errors.lock().expect("poisoned scan errors").clear();
+        self.progress.send_replace(ScanProgress::default());
+        running
+    }
+
+    fn report(&self, change: impl FnOnce(&mut ScanProgress)) {
+        self.progress.send_modify(change);
+    }
+
+    fn error(&self, path: &Path, error: &anyhow::Error) {
+        // stopping the scan isn't an error of the comic
+        if !self.is_cancelled() {
+            self.errors
+                .lock()
+                .expect("poisoned scan errors")
+                .push(ScanError {
+                    path: path.to_owned(),
+                    message: format!("{error:#}"),
+                });
+            self.report(|p| p.errors += 1);
+        }
+    }
+
+    /// end the scan and get the errors which happened during it
+    fn finish(&self) -> Vec<ScanError> {
+        let cancelled = self.is_cancelled();
+        self.report(|p| {
+            p.current_file = None;
+            p.finished = true;
+            p.cancelled = cancelled;
+        });
+
+        std::mem::take(&mut self.errors.lock().expect("poisoned scan errors"))
+    }
 }
 
-static CHAPTER_NUMBER_REGEX: LazyLock<regex::Regex> = LazyLock::new(|| {
-    regex::Regex::new(r"(\d+)\.?|\.?(\d+)").expect("invalid chapter number regex")
-});
+/// how many archives are read at the same time while scanning,
+/// reading them is mostly waiting on the disk, so there are more workers than cores
+static INSPECT_WORKERS: LazyLock<usize> =
+    LazyLock::new(|| std::thread::available_parallelism().map_or(4, |n| n.get()) * 2);
+
+/// how many directories deep chapters are searched inside a comic, e.g. `Volume 01/Part 2`
+const MAX_DIRECTORY_DEPTH: usize = 3;
 
 impl Library {
-    pub async fn new<P: AsRef<Path>>(path: P) -> Result<Self> {
+    /// open the library and scan it for changes
+    pub async fn new<P: AsRef<Path>>(path: P, scan: ScanHandle) -> Result<Self> {
+        let mut library = Self::open(path, scan).await?;
+        library.update().await?;
+
+        Ok(library)
+    }
+
+    /// open the library with the comics from the last scan
+    pub async fn open<P: AsRef<Path>>(path: P, scan: ScanHandle) -> Result<Self> {
         let db_path = path.as_ref().join(".comicdb");
         let database = Database::new(db_path).await?;
 
-        let mut library = Self {
+        Ok(Self {
             is_manga_db: true,
+            write_comic_info: false,
+            watcher: None,
+            rescan_timer: None,
+            scan,
             path: path.as_ref().into(),
             database,
+        })
+    }
+
+    /// another handle to the library, which can scan it without holding the loaded library,
+    /// it neither watches the library nor rescans it periodically
+    pub fn scanner(&self) -> Self {
+        Self {
+            database: self.database.clone(),
+            path: self.path.clone(),
+            is_manga_db: self.is_manga_db,
+            write_comic_info: self.write_comic_info,
+            watcher: None,
+            rescan_timer: None,
+            scan: self.scan.clone(),
+        }
+    }
+
+    /// change the metadata of a chapter, which is written back into its archive when enabled,
+    /// then only CBZ chapters can be changed, because the others would lose it on the next rescan,
+    /// the chapters of the comic are numbered again, returns the id of the comic
+    pub async fn update_chapter_metadata(
+        &mut self,
+        chapter_id: u32,
+        metadata: Metadata,
+    ) -> Result<u32> {
+        let mut chapter = self.database.chapter(chapter_id).await?;
+        if self.write_comic_info {
+            let path = self.abs_path(&chapter.path);
+            archive::write_metadata(&path, &metadata)?;
+
+            // the rewritten archive is neither read again nor missed after a rename,
+            // the metadata is already written, so without them it's only read again on the next scan
+            if let Ok(stats) = file_stats(&path).await {
+                (chapter.file_size, chapter.modified) = stats;
+            }
+            chapter.fingerprint = archive::fingerprint(&path).ok();
+        }
+        chapter.metadata = metadata;
+        let comic_id = chapter.comic_id;
+        self.database.update_chapter_metadata(chapter).await?;
+
+        // the number of the chapter can come from its metadata
+        let mut chapters = self.database.comic_with_chapters(comic_id).await?.chapters;
+        self.order_chapters(comic_id, &mut chapters).await?;
+        self.database.insert_chapters(chapters).await?;
+        Ok(comic_id)
+    }
+
+    /// the chapters of a comic in the order they would get with the proposed orderings,
+    /// nothing is saved
+    pub async fn preview_ordering(
+        &self,
+        comic_id: u32,
+        orderings: &[ChapterOrdering],
+    ) -> Result<Vec<Chapter>> {
+        let orderings = compile_orderings(orderings)?;
+        let mut chapters = self.database.comic_with_chapters(comic_id).await?.chapters;
+        sort_chapters(&mut chapters, &orderings);
+
+        Ok(chapters)
+    }
+
+    /// the chapters of a comic sorted with the orderings which are about to be saved,
+    /// only the edited one has to be valid, so an invalid stored one can still be fixed or deleted
+    async fn sort_with_orderings(
+        &self,
+        comic_id: u32,
+        orderings: &[ChapterOrdering],
+        edited: Option<&ChapterOrdering>,
+    ) -> Result<Vec<Chapter>> {
+        if let Some(ordering) = edited {
+            compile_ordering(ordering)?;
+        }
+        let orderings = orderings
+            .iter()
+            .sorted_by_key(|o| o.rank)
+            .filter_map(|o| compile_ordering(o).ok())
+            .collect_vec();
+        let mut chapters = self.database.comic_with_chapters(comic_id).await?.chapters;
+        sort_chapters(&mut chapters, &orderings);
+
+        Ok(chapters)
+    }
+
+    /// save a new chapter ordering and sort the chapters of its comic with it right away,
+    /// returns the id of the comic
+    pub async fn insert_chapter_ordering(&mut self, ordering: ChapterOrdering) -> Result<u32> {
+        let comic_id = ordering.comic_id;
+        let mut orderings = self.database.chapter_orderings(comic_id).await?;
+        orderings.push(ordering.clone());
+
+        let chapters = self
+            .sort_with_orderings(comic_id, &orderings, Some(&ordering))
+            .await?;
+        self.database
+            .insert_chapter_ordering(ordering, chapters)
+            .await?;
+        Ok(comic_id)
+    }
+
+    /// change a chapter ordering and sort the chapters of its comic with it right away,
+    /// returns the id of the comic
+    pub async fn update_chapter_ordering(&mut self, ordering: ChapterOrdering) -> Result<u32> {
+        // the ordering stays with its comic, whichever one the frontend has sent
+        let comic_id = self.database.chapter_ordering(ordering.id).await?.comic_id;
+        let ordering = ChapterOrdering {
+            comic_id,
+            ..ordering
         };
+        let orderings = self
+            .database
+            .chapter_orderings(comic_id)
+            .await?
+            .into_iter()
+            .map(|o| {
+                if o.id == ordering.id {
+                    ordering.clone()
+                } else {
+                    o
+                }
+            })
+            .collect_vec();
 
-        library.update().await?;
+        let chapters = self
+            .sort_with_orderings(comic_id, &orderings, Some(&ordering))
+            .await?;
+        self.database
+            .update_chapter_ordering(ordering, chapters)
+            .await?;
+        Ok(comic_id)
+    }
 
-        Ok(library)
+    /// delete a chapter ordering and sort the chapters of its comic without it right away,
+    /// returns the id of the comic
+    pub async fn delete_chapter_ordering(&mut self, id: u32) -> Result<u32> {
+        let ordering = self.database.chapter_ordering(id).await?;
+        let comic_id = ordering.comic_id;
+        let mut orderings = self.database.chapter_orderings(comic_id).await?;
+        orderings.retain(|o| o.id != id);
+
+        let chapters = self.sort_with_orderings(comic_id, &orderings, None).await?;
+        self.database
+            .delete_chapter_ordering(ordering, chapters)
+            .await?;
+        Ok(comic_id)
     }
 
     /// scan the library directory for new comics/chapters and update the database
-    async fn update(&mut self) -> Result<()> {
+    pub async fn update(&mut self) -> Result<LibraryChanges> {
+        let _running = self.scan.start().await;
+        // the scan is finished even when it fails, so its progress doesn't stay running forever
+        let changes = self.scan_library().await;
+
+        let errors = self.scan.finish();
+        self.database.replace_scan_errors(None, errors).await?;
+        changes
+    }
+
+    async fn scan_library(&mut self) -> Result<LibraryChanges> {
         let lib_comics = self.database.comics().await?;
-        // TODO: handle deletion
-        let scanned_comics = self.scan().await?;
+        let (scanned_comics, failed_dirs) = self.scan().await?;
 
-        let mut new_chapters: Vec<Chapter> = vec![];
-        let mut del_chapters: Vec<Chapter> = vec![];
+        // comics which can't be read right now are left as they are, instead of deleting them
+        let lib_comics = lib_comics
+            .into_iter()
+            .filter(|c| !failed_dirs.contains(&c.dir_path))
+            .collect();
+        self.apply_scan(lib_comics, scanned_comics).await
+    }
+
+    /// rescan some comic directories (relative to the library), e.g. after files inside of them changed,
+    /// they are compared with each other, so a renamed directory is found as well
+    pub async fn update_comics(&mut self, dirs: &[PathBuf]) -> Result<LibraryChanges> {
+        let _running = self.scan.start().await;
+        let changes = self.scan_comics(dirs).await;
+
+        let errors = self.scan.finish();
+        self.database
+            .replace_scan_errors(Some(dirs.to_vec()), errors)
+            .await?;
+        changes
+    }
+
+    async fn scan_comics(&mut self, dirs: &[PathBuf]) -> Result<LibraryChanges> {
+        let lib_comics = self
+            .database
+            .comics()
+            .await?
+            .into_iter()
+            .filter(|c| dirs.contains(&c.dir_path))
+            .collect_vec();
+        let db_comics = lib_comics
+            .iter()
+            .map(|c| (c.dir_path.clone(), c.clone()))
+            .collect();
+
+        let mut scanned_comics = vec![];
+        let mut failed_dirs = vec![];
+        for dir in dirs {
+            let abs_dir = self.abs_path(dir);
+            if !abs_dir.is_dir() || !is_not_hidden(&abs_dir) {
+                continue;
+            }
+
+            match self.create_scanned_comic(abs_dir, &db_comics).await {
+                Ok(comic) => scanned_comics.extend(has_chapters(comic)),
+                Err(e) => {
+                    self.scan.error(dir, &e);
+                    failed_dirs.push(dir);
+                }
+            }
+        }
+
+        // like in a full scan, comics which can't be read are left as they are
+        let lib_comics = lib_comics
+            .into_iter()
+            .filter(|c| !failed_dirs.contains(&&c.dir_path))
+            .collect();
+        self.apply_scan(lib_comics, scanned_comics).await
+    }
+
+    /// update the database with the difference between the comics in it and the scanned ones
+    async fn apply_scan(
+        &mut self,
+        lib_comics: Vec<Comic>,
+        scanned_comics: Vec<Comic>,
+    ) -> Result<LibraryChanges> {
+        let mut changes = LibraryChanges::default();
 
         let diff_comics = differentiate_on(lib_comics, scanned_comics, |c| &c.dir_path);
-        let mut new_comics = diff_comics.added;
-        let del_comics = diff_comics.deleted;
+        let discovered = (diff_comics.added.len() + diff_comics.kept.len()) as u32;
+        self.scan.report(|p| p.comics_discovered = discovered);
 
+        let mut lib_chapters = self
+            .database
+            .chapters()
+            .await?
+            .into_iter()
+            .into_group_map_by(|c| c.comic_id);
+
+        // chapters can be moved between comics and comic directories can be renamed,
+        // so everything which is gone is collected first and the new chapters are matched with it
+        let mut deleted = Deleted::default();
+        for c in diff_comics.deleted {
+            deleted.add_chapters(lib_chapters.remove(&c.id).unwrap_or_default());
+            deleted.comics.push(c);
+        }
+
+        let mut comics = diff_comics
+            .added
+            .into_iter()
+            .map(ComicScan::Added)
+            .collect_vec();
         for (l, s) in diff_comics.kept {
-            let lchaps = self.database.comic_with_chapters(l.id).await?.chapters;
+            let lchaps = lib_chapters.remove(&l.id).unwrap_or_default();
+            comics.push(ComicScan::Kept(ComicUpdate::new(
+                l,
+                lchaps,
+                s,
+                &mut deleted,
+            )));
+        }
+
+        // the chapters of all comics are read through one stream, so a comic with a few chapters
+        // doesn't wait for the previous one, the results come in the order of the comics
+        let paths = comics
+            .iter_mut()
+            .flat_map(ComicScan::unread_chapters)
+            .map(|c| c.path.clone())
+            .collect_vec();
+        let mut inspections = self.inspect_chapters(paths);
+
+        // every comic is committed on its own, so a cancelled scan keeps the finished ones
+        for mut c in comics {
+            if self.scan.is_cancelled() {
+                break;
+            }
+
+            let unread = c.unread_chapters().len();
+            let results = (&mut inspections).take(unread).collect::<Vec<_>>().await;
+            let path = c.dir_path().to_owned();
+            if let Err(e) = self
+                .commit_comic(c, results, &mut deleted, &mut changes)
+                .await
+            {
+                self.scan.error(&path, &e);
+            }
+            self.scan.report(|p| p.comics_processed += 1);
+        }
 
-            let diff_chapters = differentiate_on(lchaps, s.chapters, |c| &c.path);
+        // a cancelled scan leaves the deleted chapters and comics to the next one,
+        // because they might have been moved into a comic which hasn't been scanned yet
+        if !self.scan.is_cancelled() {
+            changes.deleted_comics = deleted.comics.iter().map(|c| c.id).collect();
+            self.database
+                .delete_chapters(deleted.remaining_chapters())
+                .await?;
+            self.database.delete_comics(deleted.comics).await?;
+        }
+
+        Ok(changes)
+    }
+
+    /// store a comic of the scan, after its new and modified chapters have been read
+    async fn commit_comic(
+        &mut self,
+        mut comic: ComicScan,
+        inspections: Vec<Inspection>,
+        deleted: &mut Deleted,
+        changes: &mut LibraryChanges,
+    ) -> Result<()> {
+        self.read_inspections(comic.unread_chapters(), inspections)?;
+
+        let update = match comic {
+            ComicScan::Kept(u) => u,
+            // a renamed comic directory is updated in place, so it keeps its id and orderings
+            ComicScan::Added(c) => match deleted.renamed_comic(&c.chapters) {
+                Some(l) => {
+                    self.database
+                        .move_comic(l.id, c.dir_path.clone(), c.name.clone())
+                        .await?;
+                    ComicUpdate::renamed(l, c)
+                }
+                None => {
+                    changes.added_comics.push(self.add_comic(c, deleted).await?);
+                    return Ok(());
+                }
+            },
+        };
+
+        let id = update.comic.id;
+        if self.update_comic_chapters(update, deleted).await? {
+            changes.updated_comics.push(id);
+        }
+        Ok(())
+    }
+
+    /// insert a newly found comic and return its id
+    async fn add_comic(&mut self, mut comic: Comic, deleted: &mut Deleted) -> Result<u32> {
+        deleted.match_moved(&mut comic.chapters);
+        self.order_chapters(comic.id, &mut comic.chapters).await?;
+        comic.is_manga = self.is_manga(&comic.chapters);
+
+        // the moved chapters need the id of the comic, so they are stored after it
+        let (moved, chapters) = std::mem::take(&mut comic.chapters)
+            .into_iter()
+            .partition::<Vec<_>, _>(|c| c.id != 0);
+        comic.chapters = chapters;
+        let id = self.database.insert_comics(vec![comic]).await?[0];
 
-            new_chapters.extend(diff_chapters.added);
-            del_chapters.extend(diff_chapters.deleted);
+        if !moved.is_empty() {
+            let moved = moved
+                .into_iter()
+                .map(|c| Chapter { comic_id: id, ..c })
+                .collect_vec();
+            self.database.move_chapters(moved.clone()).await?;
+            self.database.insert_chapters(moved).await?;
         }
+        Ok(id)
+    }
+
+    /// apply the changed chapters of a comic, which is already in the library,
+    /// returns if anything has changed
+    async fn update_comic_chapters(
+        &mut self,
+        update: ComicUpdate,
+        deleted: &mut Deleted,
+    ) -> Result<bool> {
+        let ComicUpdate {
+            comic: lib_comic,
+            before,
+            kept,
+            mut added,
+            outdated,
+            modified,
+        } = update;
 
-        // only get the page count when the chapter is new
-        let chaps_from_new_comics = new_comics.iter_mut().flat_map(|c| c.chapters.iter_mut());
-        for c in new_chapters.iter_mut().chain(chaps_from_new_comics) {
-            c.pages = zip::ZipArchive::new(File::open(self.abs_path(&c.path)).unwrap())
-                .unwrap()
-                .len() as u32;
+        deleted.match_moved(&mut added);
+        let moved = added.iter().filter(|c| c.id != 0).cloned().collect_vec();
+        let mut changed = modified || !added.is_empty();
+        // a broken chapter which can be read now is changed as well
+        changed |= outdated.iter().any(|c| !c.broken);
+
+        // the kept chapters can get a new number through the added ones
+        // and the fields parsed from their name are filled in after an update
+        let mut chapters = kept.into_iter().chain(outdated).chain(added).collect_vec();
+        self.order_chapters(lib_comic.id, &mut chapters).await?;
+        changed |= chapters
+            .iter()
+            .any(|c| before.get(&c.id).is_some_and(|b| b != c));
+
+        let is_manga = self.is_manga(&chapters);
+        if is_manga != lib_comic.is_manga {
+            self.database
+                .update_comics_manga(vec![(lib_comic.id, is_manga)])
+                .await?;
+            changed = true;
         }
 
-        dbg!(&new_chapters, &new_comics, &del_chapters, &del_comics);
+        if changed {
+            // the moved chapters get their new path first, so the insert updates them
+            self.database.move_chapters(moved).await?;
+            self.database.insert_chapters(chapters).await?;
+        }
 
-        self.database.insert_comics(new_comics).await?; // add the new comics
-        self.database.insert_chapters(new_chapters).await?;
-        self.database.delete_comics(del_comics).await?;
-        self.database.delete_chapters(del_chapters).await?;
+        Ok(changed)
+    }
+
+    /// read the page count, metadata and fingerprint of the chapters from their archives,
+    /// the results come in the same order as the chapters
+    fn inspect_chapters(
+        &self,
+        paths: Vec<PathBuf>,
+    ) -> impl Stream<Item = Inspection> + Send + Unpin + 'static {
+        let (library, scan) = (self.path.clone(), self.scan.clone());
+        let inspections = paths.into_iter().map(move |path| {
+            let abs_path = library.join(&path);
+            let scan = scan.clone();
+
+            tokio::task::spawn_blocking(move || {
+                if scan.is_cancelled() {
+                    return None;
+                }
+                scan.report(|p| p.current_file = Some(path));
+                let info = archive::inspect(&abs_path);
+                let fingerprint = archive::fingerprint(&abs_path).ok();
+                scan.report(|p| p.chapters_processed += 1);
+                Some((info, fingerprint))
+            })
+        });
+
+        // the archives are read with blocking io, so it's done on the blocking thread pool,
+        // at most INSPECT_WORKERS at the same time to not open thousands of files at once
+        futures::StreamExt::buffered(tokio_stream::iter(inspections), *INSPECT_WORKERS)
+    }
+
+    /// fill in the results of [`Self::inspect_chapters`],
+    /// the chapters which can't be read are marked as broken
+    fn read_inspections(
+        &self,
+        chapters: Vec<&mut Chapter>,
+        inspections: Vec<Inspection>,
+    ) -> Result<()> {
+        for (c, result) in chapters.into_iter().zip(inspections) {
+            let Some((info, fingerprint)) = result? else {
+                bail!("the scan has been cancelled");
+            };
+            c.fingerprint = fingerprint;
+
+            match info {
+                Ok(info) => {
+                    // the chapter can still be read, so it's only reported
+                    if let Some(e) = &info.metadata_error {
+                        self.scan.error(&c.path, e);
+                    }
+                    c.pages = info.pages;
+                    c.metadata = info.metadata;
+                    c.broken = false;
+                }
+                // the chapter is still added, so it shows up as broken instead of missing
+                Err(e) => {
+                    self.scan.error(&c.path, &e);
+                    c.pages = 0;
+                    c.broken = true;
+                }
+            }
+        }
 
         Ok(())
     }
 
-    /// scan the comic directory, to get every comic + chapter inside
-    async fn scan(&mut self) -> Result<Vec<Comic>> {
+    /// sort the chapters of a comic and number them in that order
+    async fn order_chapters(&self, comic_id: u32, chapters: &mut [Chapter]) -> Result<()> {
+        let chapter_orderings = self.get_chapter_orderings(comic_id).await?;
+
+        for c in chapters.iter_mut() {
+            let name = file_name::parse(&c.name);
+            c.name_title = name.title;
+            c.scanlation_group = name.group;
+            c.name_language = name.language;
+            c.display_number = display_number(c);
+        }
+        sort_chapters(chapters, &chapter_orderings);
+
+        Ok(())
+    }
+
+    /// reading direction from the `ComicInfo.xml` of the chapters,
+    /// falls back to the library default if none of them specifies it
+    fn is_manga(&self, chapters: &[Chapter]) -> bool {
+        chapters
+            .iter()
+            .find_map(|c| match c.metadata.manga? {
+                Manga::YesAndRightToLeft => Some(true),
+                Manga::Yes | Manga::No => Some(false),
+                Manga::Unknown => None,
+            })
+            .unwrap_or(self.is_manga_db)
+    }
+
+    /// scan the comic directory, to get every comic + chapter inside,
+    /// also returns the comic directories which couldn't be read
+    async fn scan(&mut self) -> Result<(Vec<Comic>, Vec<PathBuf>)> {
         let db_comics = self.comic_path_hashmap().await?;
 
-        read_entries_with_file_type(&self.path, |f: &Path| is_not_hidden(f) && f.is_dir())
-            .await?
-            // only use entries with valid paths
-            .filter_map(|d| Some(d.ok()?.path()))
-            // create comic from entry
-            .then(|d| self.create_scanned_comic(d, &db_comics))
-            .collect::<Result<Vec<_>>>()
-            .await
+        let dirs =
+            read_entries_with_file_type(&self.path, |f: &Path| is_not_hidden(f) && f.is_dir())
+                .await?
+                // only use entries with valid paths
+                .filter_map(|d| Some(d.ok()?.path()))
+                .collect::<Vec<_>>()
+                .await;
+
+        let mut comics = vec![];
+        let mut failed_dirs = vec![];
+        for d in dirs {
+            match self.create_scanned_comic(d.clone(), &db_comics).await {
+                Ok(comic) => comics.extend(has_chapters(comic)),
+                Err(e) => {
+                    let dir = self.relative_path(d);
+                    self.scan.error(&dir, &e);
+                    failed_dirs.push(dir);
+                }
+            }
+        }
+
+        Ok((comics, failed_dirs))
     }
 
     async fn create_scanned_comic(
@@ -112,75 +695,82 @@ impl Library {
             chapter_count: None,
             chapter_read: None,
             is_manga: self.is_manga_db,
+            metadata: Metadata::default(),
             cover_path: None,
             dir_path: p,
         })
     }
 
-    /// get the chapters inside a comic directory
+    /// get the chapters inside a comic directory, they are numbered later on.
+    /// subdirectories which aren't chapters themselves (e.g. volumes or arcs) are searched as well
+    /// and become the volume group of the chapters inside of them
     async fn scan_chapters<P: AsRef<Path>>(&self, path: P, comic_id: u32) -> Result<Vec<Chapter>> {
-        let mut chap_num = 1;
-        let chapter_orderings = self.get_chapter_orderings(comic_id).await?;
+        let comic_dir = path.as_ref();
+        let mut dirs = vec![(comic_dir.to_owned(), 0)];
+        let mut chaps = vec![];
 
-        let mut chap_paths = read_entries_with_file_type(path, |f| {
-            f.extension().is_some_and(|e| e == "cbz") && f.is_file()
-        })
-        .await?
-        .map(|r| r.unwrap().path())
-        // sort them by their chapter number for numbering them
-        .collect::<Vec<_>>()
-        .await;
-
-        chap_paths.sort_by_key(|p| self.chapter_number_from_path(p, &chapter_orderings));
-        let chaps = chap_paths
-            .into_iter()
-            .map(|p| {
-                let c = Chapter {
+        while let Some((dir, depth)) = dirs.pop() {
+            let paths = read_entries_with_file_type(&dir, |p: &Path| {
+                is_not_hidden(p) && (p.is_dir() || archive::is_supported(p))
+            })
+            .await?
+            .map(|r| r.map(|e| e.path()))
+            .collect::<std::io::Result<Vec<_>>>()
+            .await?;
+
+            let volume_group = dir
+                .strip_prefix(comic_dir)
+                .ok()
+                .filter(|g| !g.as_os_str().is_empty())
+                .map(|g| g.to_string_lossy().into_owned());
+
+            for p in paths {
+                // image folders are chapters, every other directory can contain chapters
+                if !archive::is_supported(&p) {
+                    // a symlink could lead back to a parent or into another comic
+                    match tokio::fs::symlink_metadata(&p).await {
+                        Ok(m) if !m.is_symlink() && depth < MAX_DIRECTORY_DEPTH => {
+                            dirs.push((p, depth + 1));
+                        }
+                        Ok(_) => (),
+                        Err(e) => self.scan.error(&self.relative_path(&p), &e.into()),
+                    }
+                    continue;
+                }
+
+                // an entry which can't be read is skipped, the rest of the comic is still scanned
+                let (file_size, modified) = match file_stats(&p).await {
+                    Ok(stats) => stats,
+                    Err(e) => {
+                        self.scan.error(&self.relative_path(&p), &e.into());
+                        continue;
+                    }
+                };
+                chaps.push(Chapter {
                     id: 0,
                     pages: 0,
-                    name: p.file_stem().unwrap().to_string_lossy().into_owned(),
+                    name: chapter_name(&p).into_owned(),
                     path: self.relative_path(p),
-                    chapter_number: chap_num,
+                    chapter_number: 0,
+                    display_number: None,
+                    volume_group: volume_group.clone(),
+                    name_title: None,
+                    scanlation_group: None,
+                    name_language: None,
+                    metadata: Metadata::default(),
                     read: 0,
+                    broken: false,
+                    file_size,
+                    modified,
+                    fingerprint: None,
                     comic_id,
-                };
-                chap_num += 1;
-                c
-            })
-            .collect_vec();
+                });
+            }
+        }
 
         Ok(chaps)
     }
 
-    /// parse the chapter number from the chapter location
-    /// this also handles in between chapters like 10.5
-    fn chapter_number_from_path(
-        &self,
-        path: &Path,
-        chapter_orderings: &[regex::Regex],
-    ) -> Vec<u32> {
-        let name = path.file_stem().unwrap_or_default().to_string_lossy();
-
-        let ordering = chapter_orderings
-            .iter()
-            .enumerate()
-            .filter(|(_, r)| r.is_match(&name))
-            .map(|(i, _)| i as u32)
-            .next();
-
-        let mut num_matches = CHAPTER_NUMBER_REGEX
-            .captures_iter(&name)
-            // get the number from either the first or second capture
-            .map(|c| c.get(1).or_else(|| c.get(2)))
-            .map(|o| o.unwrap().as_str())
-            .map(|s| s.parse::<u32>().unwrap())
-            .collect_vec();
-
-        // begin with the ordering and if no right ordering has been found just use the biggest possible
-        num_matches.insert(0, ordering.unwrap_or(u32::MAX));
-        num_matches
-    }
-
     async fn comic_path_hashmap(&self) -> Result<HashMap<PathBuf, Comic>> {
         Ok(self
             .database
@@ -191,14 +781,27 @@ impl Library {
             .collect())
     }
 
+    /// the stored orderings of a comic by their rank, the invalid ones (e.g. saved by an older version)
+    /// are skipped and reported, so they don't stop the comic from being scanned
     async fn get_chapter_orderings(&self, comic_id: u32) -> Result<Vec<regex::Regex>> {
-        Ok(self
+        let mut regexes = vec![];
+        for ordering in self
             .database
             .chapter_orderings(comic_id)
             .await?
             .into_iter()
-            .map(|o| regex::Regex::new(&o.regex))
-            .collect::<Result<_, _>>()?)
+            .sorted_by_key(|o| o.rank)
+        {
+            match compile_ordering(&ordering) {
+                Ok(regex) => regexes.push(regex),
+                Err(e) => {
+                    let comic = self.database.comic(comic_id).await?;
+                    self.scan.error(&comic.dir_path, &e);
+                }
+            }
+        }
+
+        Ok(regexes)
     }
 
     fn relative_path<P: AsRef<Path>>(&self, path: P) -> PathBuf {
@@ -213,6 +816,175 @@ impl Library {
     }
 }
 
+/// the result of reading a chapter in [`Library::inspect_chapters`], `None` when the scan is cancelled
+type Inspection = std::result::Result<
+    Option<(Result<archive::ArchiveInfo>, Option<String>)>,
+    tokio::task::JoinError,
+>;
+
+/// a comic of the scan, which is committed after its new and modified chapters have been read
+enum ComicScan {
+    Added(Comic),
+    Kept(ComicUpdate),
+}
+
+impl ComicScan {
+    fn dir_path(&self) -> &Path {
+        match self {
+            ComicScan::Added(c) => &c.dir_path,
+            ComicScan::Kept(u) => &u.comic.dir_path,
+        }
+    }
+
+    /// the chapters which have to be read before the comic is committed
+    fn unread_chapters(&mut self) -> Vec<&mut Chapter> {
+        match self {
+            ComicScan::Added(c) => c.chapters.iter_mut().collect(),
+            ComicScan::Kept(u) => u.added.iter_mut().chain(&mut u.outdated).collect(),
+        }
+    }
+}
+
+/// the changed chapters of a comic, which is already in the library
+struct ComicUpdate {
+    comic: Comic,
+    /// the chapters in the library by id, to notice which ones have changed
+    before: HashMap<u32, Chapter>,
+    kept: Vec<Chapter>,
+    added: Vec<Chapter>,
+    /// modified and broken chapters, which are read again
+    outdated: Vec<Chapter>,
+    /// if any of the chapters has been modified or deleted on disk
+    modified: bool,
+}
+
+impl ComicUpdate {
+    /// the deleted chapters of the comic are added to the others of the scan
+    fn new(lib_comic: Comic, lchaps: Vec<Chapter>, scanned: Comic, deleted: &mut Deleted) -> Self {
+        let before = lchaps.iter().map(|c| (c.id, c.clone())).collect();
+        let diff_chapters = differentiate_on(lchaps, scanned.chapters, |c| &c.path);
+
+        // modified chapters are read again to refresh their page count and metadata,
+        // broken ones too, because they might have been fixed in the meantime
+        let (outdated, kept): (Vec<_>, Vec<_>) = diff_chapters
+            .kept
+            .into_iter()
+            .partition(|(l, s)| l.broken || !same_file(l, s));
+        let modified =
+            !diff_chapters.deleted.is_empty() || outdated.iter().any(|(l, s)| !same_file(l, s));
+        let outdated = outdated
+            .into_iter()
+            .map(|(l, s)| Chapter {
+                file_size: s.file_size,
+                modified: s.modified,
+                ..l
+            })
+            .collect();
+        deleted.add_chapters(diff_chapters.deleted);
+
+        Self {
+            comic: lib_comic,
+            before,
+            kept: kept.into_iter().map(|(l, _)| l).collect(),
+            // only get the page count and metadata when the chapter is new
+            added: diff_chapters.added,
+            outdated,
+            modified,
+        }
+    }
+
+    /// a deleted comic, which has been found in another directory,
+    /// its old chapters are with the deleted ones of the scan and are found as moved
+    fn renamed(mut lib_comic: Comic, scanned: Comic) -> Self {
+        lib_comic.dir_path = scanned.dir_path;
+        lib_comic.name = scanned.name;
+        let added = scanned
+            .chapters
+            .into_iter()
+            .map(|c| Chapter {
+                comic_id: lib_comic.id,
+                ..c
+            })
+            .collect();
+
+        Self {
+            comic: lib_comic,
+            before: HashMap::new(),
+            kept: vec![],
+            added,
+            outdated: vec![],
+            modified: true,
+        }
+    }
+}
+
+/// the comics and chapters which are gone after a scan, unless they are found somewhere else,
+/// a chapter with the same fingerprint is the same file, which has been moved or renamed
+#[derive(Default)]
+struct Deleted {
+    comics: Vec<Comic>,
+    chapters: HashMap<String, Chapter>,
+    /// the chapters without fingerprint and the duplicates, which can't be told apart
+    unmatched: Vec<Chapter>,
+}
+
+impl Deleted {
+    fn add_chapters(&mut self, chapters: Vec<Chapter>) {
+        for c in chapters {
+            match c.fingerprint.clone() {
+                Some(f) => self.unmatched.extend(self.chapters.insert(f, c)),
+                None => self.unmatched.push(c),
+            }
+        }
+    }
+
+    /// the deleted comic, which one of the chapters has been in
+    fn renamed_comic(&mut self, chapters: &[Chapter]) -> Option<Comic> {
+        let comic_id = chapters.iter().find_map(|c| {
+            let old = self.chapters.get(c.fingerprint.as_ref()?)?;
+            self.comics
+                .iter()
+                .any(|l| l.id == old.comic_id)
+                .then_some(old.comic_id)
+        })?;
+        let i = self.comics.iter().position(|l| l.id == comic_id)?;
+        Some(self.comics.swap_remove(i))
+    }
+
+    /// the added chapters, which have been moved, take over the id and read progress
+    fn match_moved(&mut self, added: &mut [Chapter]) {
+        for c in added {
+            if let Some(old) = c.fingerprint.as_ref().and_then(|f| self.chapters.remove(f)) {
+                c.id = old.id;
+                c.read = old.read;
+            }
+        }
+    }
+
+    /// the chapters which are really deleted
+    fn remaining_chapters(&mut self) -> Vec<Chapter> {
+        let mut chapters = std::mem::take(&mut self.unmatched);
+        chapters.extend(self.chapters.drain().map(|(_, c)| c));
+        chapters
+    }
+}
+
+/// size and modification time (unix millis) of a file, to notice when it has changed
+async fn file_stats(path: &Path) -> std::io::Result<(u64, i64)> {
+    let metadata = tokio::fs::metadata(path).await?;
+    let modified = metadata
+        .modified()?
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as i64);
+
+    Ok((metadata.len(), modified))
+}
+
+/// checks if the archive of the chapter is still the same as when it was read
+fn same_file(a: &Chapter, b: &Chapter) -> bool {
+    a.file_size == b.file_size && a.modified == b.modified
+}
+
 async fn read_entries_with_file_type<P, FE>(
     path: P,
     pred_entry: FE,
@@ -236,9 +1008,833 @@ where
     Ok(result)
 }
 
+/// the regexes of the orderings by their rank, fails on the first invalid one
+fn compile_orderings(orderings: &[ChapterOrdering]) -> Result<Vec<regex::Regex>> {
+    orderings
+        .iter()
+        .sorted_by_key(|o| o.rank)
+        .map(compile_ordering)
+        .collect()
+}
+
+fn compile_ordering(ordering: &ChapterOrdering) -> Result<regex::Regex> {
+    regex::Regex::new(&ordering.regex)
+        .with_context(|| format!("invalid chapter ordering regex `{}`", ordering.regex))
+}
+
+/// sort the chapters and number them in that order
+fn sort_chapters(chapters: &mut [Chapter], chapter_orderings: &[regex::Regex]) {
+    chapters.sort_by_cached_key(|c| chapter_sort_key(c, chapter_orderings));
+    for (i, c) in chapters.iter_mut().enumerate() {
+        c.chapter_number = i as u32 + 1;
+    }
+}
+
+/// key to sort the chapters by, the matching chapter ordering comes first,
+/// then the volume group and the display number, chapters without a number come first
+fn chapter_sort_key(chapter: &Chapter, chapter_orderings: &[regex::Regex]) -> impl Ord {
+    let ordering = chapter_orderings
+        .iter()
+        .position(|r| r.is_match(&chapter.name))
+        .map(|i| i as u32);
+    let group = chapter.volume_group.as_deref();
+
+    (
+        // if no right ordering has been found just use the biggest possible
+        ordering.unwrap_or(u32::MAX),
+        // chapters outside of a group are usually the newest ones, which aren't in a volume yet
+        group.is_none(),
+        group.map(|g| (group_numbers(g), g.to_owned())),
+        chapter
+            .display_number
+            .as_ref()
+            .map(chapter_number::sort_key),
+        chapter.name.clone(),
+    )
+}
+
+/// the numbers in the name of a volume group, e.g. `[2]` for `Season 2` or `[1, 3]` for `Volume 01/Arc 3`
+fn group_numbers(group: &str) -> Vec<u64> {
+    group
+        .split(|c: char| !c.is_ascii_digit())
+        .filter_map(|n| n.parse().ok())
+        .collect()
+}
+
+/// the chapter number from the `ComicInfo.xml`, the missing parts are taken from the chapter name
+/// and the volume can also come from the volume group
+fn display_number(chapter: &Chapter) -> Option<ChapterNumber> {
+    let metadata = &chapter.metadata;
+    let info = metadata
+        .number
+        .as_deref()
+        .and_then(chapter_number::parse)
+        .unwrap_or_default();
+    let name = chapter_number::parse(&chapter.name).unwrap_or_default();
+    let group_volume = chapter
+        .volume_group
+        .as_deref()
+        .and_then(chapter_number::parse)
+        .and_then(|n| n.volume);
+
+    let (number, suffix) = match info.number {
+        Some(_) => (info.number, info.suffix),
+        None => (name.number, name.suffix),
+    };
+    let number = ChapterNumber {
+        volume: metadata
+            .volume
+            .and_then(|v| u32::try_from(v).ok())
+            .or(info.volume)
+            .or(name.volume)
+            .or(group_volume),
+        number,
+        suffix,
+        extra: info.extra || name.extra,
+    };
+
+    (number != ChapterNumber::default()).then_some(number)
+}
+
+/// name of the chapter without the file extension,
+/// folder chapters keep their full name because dots are part of it (e.g. `Chapter 10.5`)
+fn chapter_name(path: &Path) -> Cow<'_, str> {
+    let name = if path.is_dir() {
+        path.file_name()
+    } else {
+        path.file_stem()
+    };
+
+    name.unwrap_or_default().to_string_lossy()
+}
+
+/// directories without any chapters aren't comics (yet)
+fn has_chapters(comic: Comic) -> Option<Comic> {
+    (!comic.chapters.is_empty()).then_some(comic)
+}
+
 fn is_not_hidden(entry: &Path) -> bool {
     entry
         .file_name()
         .and_then(|n| n.to_string_lossy().chars().next())
         .is_some_and(|c| c != '.')
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{fs::File, io::Write};
+
+    /// create a cbz with a single page
+    fn create_chapter(path: &Path) -> Result<()> {
+        create_chapter_with_pages(path, 1)
+    }
+
+    fn create_chapter_with_pages(path: &Path, pages: usize) -> Result<()> {
+        let mut zip = zip::ZipWriter::new(File::create(path)?);
+        for i in 1..=pages {
+            zip.start_file(format!("{i:03}.png"), Default::default())?;
+            zip.write_all(b"\x89PNG\r\n\x1a\npage")?;
+        }
+        zip.finish()?;
+        Ok(())
+    }
+
+    fn chapter(name: &str, number: Option<&str>, volume: Option<i32>) -> Chapter {
+        Chapter {
+            id: 0,
+            pages: 0,
+            name: name.into(),
+            path: name.into(),
+            chapter_number: 0,
+            display_number: None,
+            volume_group: None,
+            name_title: None,
+            scanlation_group: None,
+            name_language: None,
+            metadata: Metadata {
+                number: number.map(Into::into),
+                volume,
+                ..Default::default()
+            },
+            read: 0,
+            broken: false,
+            file_size: 0,
+            modified: 0,
+            fingerprint: None,
+            comic_id: 0,
+        }
+    }
+
+    #[test]
+    fn display_number_prefers_comic_info() {
+        let number = |volume, number, extra| {
+            Some(ChapterNumber {
+                volume,
+                number: Some(number),
+                suffix: None,
+                extra,
+            })
+        };
+
+        assert_eq!(
+            display_number(&chapter("Chapter 10.5", None, None)),
+            number(None, 10.5, false)
+        );
+        assert_eq!(
+            display_number(&chapter("Chapter 99", Some("3"), Some(2))),
+            number(Some(2), 3.0, false)
+        );
+        // a number without digits still marks the chapter as an extra
+        assert_eq!(
+            display_number(&chapter("Chapter 4", Some("Extra"), Some(1))),
+            number(Some(1), 4.0, true)
+        );
+        // chapters in volume directories get their volume from it
+        let mut grouped = chapter("Chapter 3", None, None);
+        grouped.volume_group = Some("Volume 02".into());
+        assert_eq!(display_number(&grouped), number(Some(2), 3.0, false));
+
+        // the chapter orderings still come first
+        let special = [regex::Regex::new("Special").unwrap()];
+        let key = |name: &str, number: Option<&str>| {
+            let mut c = chapter(name, number, None);
+            c.display_number = display_number(&c);
+            chapter_sort_key(&c, &special)
+        };
+        assert!(key("Special 1", Some("7")) < key("Chapter 1", None));
+        assert!(key("Chapter 2", None) < key("Chapter 10", None));
+    }
+
+    #[test]
+    fn volume_groups_sort_chapters() {
+        let grouped = |group: Option<&str>, name: &str| {
+            let mut c = chapter(name, None, None);
+            c.volume_group = group.map(Into::into);
+            c.display_number = display_number(&c);
+            c
+        };
+        // both seasons start at chapter 1 again
+        let mut chapters = vec![
+            grouped(None, "Chapter 1"),
+            grouped(Some("Season 10"), "Chapter 1"),
+            grouped(Some("Season 2"), "Chapter 2"),
+            grouped(Some("Season 2"), "Chapter 1"),
+            grouped(Some("Arc 03 - Conviction"), "Chapter 1"),
+            grouped(Some("Extras"), "Chapter 1"),
+        ];
+        sort_chapters(&mut chapters, &[]);
+
+        assert_eq!(
+            chapters
+                .iter()
+                .map(|c| (c.volume_group.as_deref(), c.name.as_str()))
+                .collect_vec(),
+            vec![
+                (Some("Extras"), "Chapter 1"),
+                (Some("Season 2"), "Chapter 1"),
+                (Some("Season 2"), "Chapter 2"),
+                (Some("Arc 03 - Conviction"), "Chapter 1"),
+                (Some("Season 10"), "Chapter 1"),
+                (None, "Chapter 1"),
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn scan_reports_progress_and_errors() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter(&berserk.join("Chapter 2.cbz"))?;
+        create_chapter(&berserk.join("Chapter 1.cbz"))?;
+
+        let broken = dir.path().join("Broken");
+        std::fs::create_dir(&broken)?;
+        std::fs::write(broken.join("Chapter 1.cbz"), "no zip")?;
+
+        let scan = ScanHandle::default();
+        let library = Library::new(dir.path(), scan.clone()).await?;
+
+        let progress = scan.subscribe().borrow().clone();
+        assert_eq!(progress.comics_discovered, 2);
+        assert_eq!(progress.comics_processed, 2);
+        assert_eq!(progress.chapters_processed, 3);
+        assert_eq!(progress.errors, 1);
+        assert!(progress.finished && !progress.cancelled);
+
+        let comics = library.database.comics().await?;
+        assert_eq!(comics.len(), 2);
+        let chapters = library
+            .database
+            .comic_with_chapters(comics[0].id)
+            .await?
+            .chapters;
+        assert_eq!(chapters[0].name, "Chapter 1");
+        assert_eq!(chapters[0].pages, 1);
+        assert!(!chapters[0].broken);
+
+        // the broken chapter is still added and the reason is in the scan report
+        let broken_chapters = library
+            .database
+            .comic_with_chapters(comics[1].id)
+            .await?
+            .chapters;
+        assert!(broken_chapters[0].broken);
+        let errors = library.database.scan_errors().await?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, Path::new("Broken").join("Chapter 1.cbz"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scan_volume_directories() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        let (volume1, volume2) = (berserk.join("Volume 01"), berserk.join("Volume 02"));
+        std::fs::create_dir_all(volume1.join("Chapter 002"))?;
+        std::fs::create_dir(&volume2)?;
+        create_chapter(&volume2.join("Chapter 010.cbz"))?;
+        create_chapter(&volume1.join("Chapter 001.cbz"))?;
+        // an image folder is a chapter and not another volume
+        std::fs::write(
+            volume1.join("Chapter 002").join("001.png"),
+            b"\x89PNG\r\n\x1a\n",
+        )?;
+
+        let library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comics = library.database.comics().await?;
+        assert_eq!(comics.len(), 1);
+
+        let chapters = library
+            .database
+            .comic_with_chapters(comics[0].id)
+            .await?
+            .chapters;
+        assert_eq!(
+            chapters
+                .iter()
+                .map(|c| (c.name.as_str(), c.volume_group.as_deref()))
+                .collect_vec(),
+            vec![
+                ("Chapter 001", Some("Volume 01")),
+                ("Chapter 002", Some("Volume 01")),
+                ("Chapter 010", Some("Volume 02")),
+            ]
+        );
+        assert_eq!(chapters[1].pages, 1);
+
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn scan_skips_symlinked_directories() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (berserk, vagabond) = (dir.path().join("Berserk"), dir.path().join("Vagabond"));
+        std::fs::create_dir_all(berserk.join("Volume 01"))?;
+        std::fs::create_dir(&vagabond)?;
+        create_chapter(&berserk.join("Volume 01").join("Chapter 001.cbz"))?;
+        create_chapter(&vagabond.join("Chapter 001.cbz"))?;
+        std::os::unix::fs::symlink(&berserk, berserk.join("Volume 01").join("Loop"))?;
+        std::os::unix::fs::symlink(&vagabond, berserk.join("Vagabond"))?;
+        // too deep to be a volume
+        let deep = berserk.join("a").join("b").join("c").join("d");
+        std::fs::create_dir_all(&deep)?;
+        create_chapter(&deep.join("Chapter 002.cbz"))?;
+
+        let library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comics = library.database.comics().await?;
+        assert_eq!(comics.len(), 2);
+
+        let chapters = library
+            .database
+            .comic_with_chapters(comics[0].id)
+            .await?
+            .chapters;
+        assert_eq!(
+            chapters.iter().map(|c| c.path.clone()).collect_vec(),
+            vec![Path::new("Berserk/Volume 01/Chapter 001.cbz")]
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn unreadable_chapters_are_reported() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter(&berserk.join("Chapter 1.cbz"))?;
+        // the target of the link has been deleted
+        std::os::unix::fs::symlink(berserk.join("gone.cbz"), berserk.join("Chapter 2.cbz"))?;
+
+        let library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comics = library.database.comics().await?;
+        assert_eq!(comics.len(), 1);
+        let chapters = library
+            .database
+            .comic_with_chapters(comics[0].id)
+            .await?
+            .chapters;
+        assert_eq!(chapters.len(), 1);
+        assert_eq!(chapters[0].name, "Chapter 1");
+
+        let errors = library.database.scan_errors().await?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, Path::new("Berserk").join("Chapter 2.cbz"));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn preview_ordering_doesnt_save() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        for name in ["Chapter 1", "Chapter 2", "Special 1"] {
+            create_chapter(&berserk.join(format!("{name}.cbz")))?;
+        }
+
+        let library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comic = library.database.comics().await?[0].clone();
+        let ordering = |rank, regex: &str| ChapterOrdering {
+            id: 0,
+            comic_id: comic.id,
+            rank,
+            regex: regex.into(),
+        };
+        let names = |chapters: Vec<Chapter>| {
+            chapters
+                .into_iter()
+                .map(|c| (c.chapter_number, c.name))
+                .collect_vec()
+        };
+
+        let preview = library
+            .preview_ordering(
+                comic.id,
+                &[ordering(2, "Chapter 2"), ordering(1, "^Special")],
+            )
+            .await?;
+        assert_eq!(
+            names(preview),
+            [
+                (1, "Special 1".to_owned()),
+                (2, "Chapter 2".to_owned()),
+                (3, "Chapter 1".to_owned()),
+            ]
+        );
+
+        let invalid = library
+            .preview_ordering(comic.id, &[ordering(1, "Chapter (")])
+            .await;
+        assert!(invalid.unwrap_err().to_string().contains("Chapter ("));
+
+        let saved = library.database.comic_with_chapters(comic.id).await?;
+        assert_eq!(names(saved.chapters)[0], (1, "Chapter 1".to_owned()));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn orderings_sort_chapters_right_away() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        for name in ["Chapter 1", "Chapter 2", "Special 1"] {
+            create_chapter(&berserk.join(format!("{name}.cbz")))?;
+        }
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comic_id = library.database.comics().await?[0].id;
+        let names = |comic: Comic| comic.chapters.into_iter().map(|c| c.name).collect_vec();
+
+        library
+            .insert_chapter_ordering(ChapterOrdering {
+                id: 0,
+                comic_id,
+                rank: 1,
+                regex: "^Special".into(),
+            })
+            .await?;
+        let comic = library.database.comic_with_chapters(comic_id).await?;
+        assert_eq!(names(comic), ["Special 1", "Chapter 1", "Chapter 2"]);
+
+        let mut ordering = library.database.chapter_orderings(comic_id).await?[0].clone();
+        ordering.regex = "2$".into();
+        // the ordering stays with its comic, even if another one is sent
+        ordering.comic_id = comic_id + 1;
+        assert_eq!(
+            library.update_chapter_ordering(ordering.clone()).await?,
+            comic_id
+        );
+        let comic = library.database.comic_with_chapters(comic_id).await?;
+        assert_eq!(names(comic), ["Chapter 2", "Chapter 1", "Special 1"]);
+
+        // an invalid regex is neither saved nor breaks the next scan
+        ordering.regex = "Chapter (".into();
+        assert!(library
+            .update_chapter_ordering(ordering.clone())
+            .await
+            .is_err());
+        assert_eq!(
+            library.database.chapter_orderings(comic_id).await?[0].regex,
+            "2$"
+        );
+
+        library.delete_chapter_ordering(ordering.id).await?;
+        let comic = library.database.comic_with_chapters(comic_id).await?;
+        assert_eq!(names(comic), ["Chapter 1", "Chapter 2", "Special 1"]);
+        assert!(library
+            .database
+            .chapter_orderings(comic_id)
+            .await?
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn invalid_stored_orderings_are_skipped() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        for name in ["Chapter 1", "Special 1"] {
+            create_chapter(&berserk.join(format!("{name}.cbz")))?;
+        }
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comic_id = library.database.comics().await?[0].id;
+        let names = |comic: Comic| comic.chapters.into_iter().map(|c| c.name).collect_vec();
+        // e.g. saved before the regexes were checked
+        let invalid = ChapterOrdering {
+            id: 0,
+            comic_id,
+            rank: 1,
+            regex: "Chapter (".into(),
+        };
+        library
+            .database
+            .insert_chapter_ordering(invalid, vec![])
+            .await?;
+
+        create_chapter(&berserk.join("Chapter 2.cbz"))?;
+        library.update().await?;
+        let comic = library.database.comic_with_chapters(comic_id).await?;
+        assert_eq!(names(comic), ["Chapter 1", "Chapter 2", "Special 1"]);
+        let errors = library.database.scan_errors().await?;
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].path, Path::new("Berserk"));
+
+        // the other orderings can still be edited and the invalid one deleted
+        library
+            .insert_chapter_ordering(ChapterOrdering {
+                id: 0,
+                comic_id,
+                rank: 2,
+                regex: "^Special".into(),
+            })
+            .await?;
+        let comic = library.database.comic_with_chapters(comic_id).await?;
+        assert_eq!(names(comic), ["Special 1", "Chapter 1", "Chapter 2"]);
+
+        let orderings = library.database.chapter_orderings(comic_id).await?;
+        let invalid = orderings.iter().find(|o| o.regex == "Chapter (").unwrap();
+        library.delete_chapter_ordering(invalid.id).await?;
+        assert_eq!(library.database.chapter_orderings(comic_id).await?.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn written_metadata_isnt_read_again() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter(&berserk.join("Chapter 1.cbz"))?;
+        create_chapter_with_pages(&berserk.join("Chapter 2.cbz"), 2)?;
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        library.write_comic_info = true;
+        let comic = library.database.comics().await?[0].clone();
+        let chapter = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters[0]
+            .clone();
+
+        let metadata = Metadata {
+            title: Some("The Black Swordsman".into()),
+            number: Some("3".into()),
+            ..Default::default()
+        };
+        let comic_id = library
+            .update_chapter_metadata(chapter.id, metadata.clone())
+            .await?;
+        assert_eq!(comic_id, comic.id);
+        let updated = library.database.chapter(chapter.id).await?;
+        assert_eq!(updated.metadata, metadata);
+        assert_ne!(updated.fingerprint, chapter.fingerprint);
+        // the new number moves it behind chapter 2
+        assert_eq!(updated.chapter_number, 2);
+        assert_eq!(updated.display_number.and_then(|n| n.number), Some(3.0));
+
+        // the stored file stats match the rewritten archive
+        assert!(library.update().await?.is_empty());
+        assert_eq!(library.scan.subscribe().borrow().chapters_processed, 0);
+
+        // and its new fingerprint is found after a rename
+        std::fs::rename(berserk.join("Chapter 1.cbz"), berserk.join("Ch 1.cbz"))?;
+        library.update().await?;
+        let renamed = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters;
+        assert_eq!(renamed[1].id, chapter.id);
+        assert_eq!(renamed[1].metadata, metadata);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn scanner_updates_the_loaded_library() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter(&berserk.join("Chapter 1.cbz"))?;
+        let library = Library::open(dir.path(), ScanHandle::default()).await?;
+
+        // the second scan waits for the first one
+        let (mut first, mut second) = (library.scanner(), library.scanner());
+        let (first, second) = tokio::join!(first.update(), second.update());
+        assert_eq!(first?.added_comics.len(), 1);
+        assert!(second?.added_comics.is_empty());
+
+        assert_eq!(library.database.comics().await?.len(), 1);
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn failed_scan_is_finished() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let library_dir = dir.path().join("library");
+        std::fs::create_dir(&library_dir)?;
+
+        let mut library = Library::new(&library_dir, ScanHandle::default()).await?;
+        // e.g. the drive of the library was unmounted, the open database keeps working
+        std::fs::rename(&library_dir, dir.path().join("moved"))?;
+        assert!(library.update().await.is_err());
+        assert!(library.scan.subscribe().borrow().finished);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn rescan_only_reads_changed_chapters() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter(&berserk.join("Chapter 1.cbz"))?;
+        create_chapter(&berserk.join("Chapter 2.cbz"))?;
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comic = library.database.comics().await?[0].clone();
+        let chapters = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters;
+        library
+            .database
+            .update_chapter_page(chapters[1].id, 1)
+            .await?;
+
+        // nothing changed, so no archive is read again
+        assert!(library.update().await?.is_empty());
+        assert_eq!(library.scan.subscribe().borrow().chapters_processed, 0);
+
+        // a re-downloaded chapter with more pages
+        create_chapter_with_pages(&berserk.join("Chapter 2.cbz"), 3)?;
+        let changes = library.update().await?;
+        assert_eq!(changes.updated_comics, vec![comic.id]);
+        assert_eq!(library.scan.subscribe().borrow().chapters_processed, 1);
+
+        let chapters = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters;
+        assert_eq!(chapters[1].pages, 3);
+        assert_eq!(chapters[1].read, 1);
+        assert_eq!(chapters[0].pages, 1);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn renames_keep_progress() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let berserk = dir.path().join("Berserk");
+        std::fs::create_dir(&berserk)?;
+        create_chapter_with_pages(&berserk.join("ch1.cbz"), 1)?;
+        create_chapter_with_pages(&berserk.join("ch2.cbz"), 2)?;
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comic = library.database.comics().await?[0].clone();
+        let chapters = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters;
+        library
+            .database
+            .update_chapter_page(chapters[0].id, 1)
+            .await?;
+        library
+            .database
+            .update_chapter_page(chapters[1].id, 2)
+            .await?;
+
+        std::fs::rename(berserk.join("ch1.cbz"), berserk.join("Chapter 001.cbz"))?;
+        let changes = library.update().await?;
+        assert_eq!(changes.updated_comics, vec![comic.id]);
+        let renamed = library
+            .database
+            .comic_with_chapters(comic.id)
+            .await?
+            .chapters;
+        assert_eq!(renamed[0].id, chapters[0].id);
+        assert_eq!(renamed[0].name, "Chapter 001");
+        assert_eq!(renamed[0].read, 1);
+
+        // the watcher updates the old and new directory together
+        std::fs::rename(&berserk, dir.path().join("Berserk (1989)"))?;
+        let changes = library
+            .update_comics(&["Berserk".into(), "Berserk (1989)".into()])
+            .await?;
+        assert!(changes.added_comics.is_empty() && changes.deleted_comics.is_empty());
+        assert_eq!(changes.updated_comics, vec![comic.id]);
+
+        let moved = library.database.comic_with_chapters(comic.id).await?;
+        assert_eq!(moved.name, "Berserk (1989)");
+        assert_eq!(moved.dir_path, Path::new("Berserk (1989)"));
+        assert_eq!(
+            moved.chapters.iter().map(|c| (c.id, c.read)).collect_vec(),
+            vec![(chapters[0].id, 1), (chapters[1].id, 2)]
+        );
+        assert_eq!(
+            moved.chapters[1].path,
+            Path::new("Berserk (1989)").join("ch2.cbz")
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn moved_chapters_keep_progress() -> Result<()> {
+        let dir = tempfile::tempdir()?;
+        let (a, b, c) = (
+            dir.path().join("A"),
+            dir.path().join("B"),
+            dir.path().join("C"),
+        );
+        std::fs::create_dir(&a)?;
+        std::fs::create_dir(&b)?;
+        // the page counts keep the fingerprints apart
+        create_chapter_with_pages(&a.join("ch1.cbz"), 1)?;
+        create_chapter_with_pages(&a.join("ch2.cbz"), 2)?;
+        create_chapter_with_pages(&a.join("ch3.cbz"), 3)?;
+        create_chapter_with_pages(&b.join("ch1.cbz"), 4)?;
+
+        let mut library = Library::new(dir.path(), ScanHandle::default()).await?;
+        let comics = library.database.comics().await?;
+        let chapters = library
+            .database
+            .comic_with_chapters(comics[0].id)
+            .await?
+            .chapters;
+        for c in &chapters[..2] {
+            library.database.update_chapter_page(c.id, c.pages).await?;
+        }
+
+        // into another comic and into a new one
+        std::fs::create_dir(&c)?;
+        std::fs::rename(a.join("ch1.cbz"), b.join("ch2.cbz"))?;
+        std::fs::rename(a.join("ch2.cbz"), c.join("ch1.cbz"))?;
+        let changes = library.update().await?;
+        assert_eq!(changes.added_comics.len(), 1);
+        assert!(changes.deleted_comics.is_empty());
+
+        let b = library.database.comic_with_chapters(comics[1].id).await?;
+        assert_eq!(
+            b.chapters.iter().map(|c| (c.id, c.read)).collect_vec(),
+            vec![(b.chapters[0].id, 0), (chapters[0].id, 1)]
+        );
+        let c = library
+            .database
+            .comic_with_chapters(changes.added_comics[0])
+            .await?;
+        assert_eq!(c.name, "C");
+        assert_eq!(
+            c.chapters.iter().map(|c| (c.id, c.read)).collect_vec(),
+            vec![(chapters[1].id, 2)]
+        );
+        let a = library.database.comic_with_chapters(comics[0].id).await?;
+        assert_eq!(a.chapters.len(), 1);
+
+        Ok(())
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn scan_large_library() -> Result<()> {
+        const COMICS: usize = 20;
+        const CHAPTERS: usize = 50;
+
+        let dir = tempfile::tempdir()?;
+        for comic in 0..COMICS {
+            let comic_dir = dir.path().join(format!("Comic {comic}"));
+            std::fs::create_dir(&comic_dir)?;
+            // the page count tells the comics apart, they are read through the same stream
+            for chapter in 1..=CHAPTERS {
+                let path = comic_dir.join(format!("Chapter {chapter}.cbz"));
+                create_chapter_with_pages(&path, comic % 3 + 1)?;
+            }
+        }
+
+        let start = std::time::Instant::now();
+        let scan = ScanHandle::default();
+        let library = Library::new(dir.path(), scan.clone()).await?;
+        let elapsed = start.elapsed();
+        println!(
+            "scanned {} chapters in {elapsed:?} ({:?} per chapter, {} workers)",
+            COMICS * CHAPTERS,
+            elapsed / (COMICS * CHAPTERS) as u32,
+            *INSPECT_WORKERS
+        );
+
+        let progress = scan.subscribe().borrow().clone();
+        assert_eq!(progress.chapters_processed as usize, COMICS * CHAPTERS);
+        assert_eq!(progress.errors, 0);
+
+        let comics = library.database.comics().await?;
+        assert_eq!(comics.len(), COMICS);
+        for comic in comics {
+            let pages = comic.name["Comic ".len()..].parse::<u32>()? % 3 + 1;
+            let chapters = library
+                .database
+                .comic_with_chapters(comic.id)
+                .await?
+                .chapters;
+            assert_eq!(chapters.len(), CHAPTERS);
+            // the parallel inspection keeps the page count with the right chapter
+            assert!(chapters.iter().all(|c| c.pages == pages && !c.broken));
+            assert_eq!(chapters[9].name, "Chapter 10");
+        }
+
+        Ok(())
+    }
+}
@@ -10,9 +10,10 @@
     windows_subsystem = "windows"
 )]
 
-use std::{error::Error, fs::File, io::BufReader, io::Read, path::Path};
+use std::error::Error;
 
 use api::{LibState, SettingsState};
+use library::ScanHandle;
 use settings::Settings;
 use tauri::{
     http::{self, ResponseBuilder},
@@ -22,13 +23,16 @@ use tokio::runtime::Handle;
 use url::Url;
 
 mod api;
+mod archive;
 mod db;
 mod differentiation;
 mod directories;
 mod entities;
 mod library;
+mod metadata;
 mod settings;
 mod util;
+mod watcher;
 
 fn get_comic_page<R: Runtime>(
     app: &AppHandle<R>,
@@ -58,34 +62,50 @@ fn get_comic_page<R: Runtime>(
         .ok_or("Missing query param: page")?
         .parse::<usize>()?;
 
-    let file = File::open(path)?;
-    let r = BufReader::new(file);
-    let mut zip = zip::ZipArchive::new(r)?;
+    let page = archive::read_page(path, page - 1)?;
 
-    let mut page = zip.by_index(page - 1)?;
-    let mut content = Vec::new();
-    page.read_to_end(&mut content)?;
-
-    let path: &Path = page.name().as_ref();
-    let ext = path
-        .extension()
-        .ok_or("no extension in cbz file")?
-        .to_string_lossy();
+    // the webview can't show it anyway, so tell what's wrong instead of sending a broken image
+    let Some(format) = page.format else {
+        return ResponseBuilder::new()
+            .status(415)
+            .mimetype("text/plain")
+            .body(format!("page {} is no supported image", page.name).into_bytes());
+    };
 
     ResponseBuilder::new()
         .status(200)
-        .mimetype(&format!("image/{}", &ext))
-        .body(content)
+        .mimetype(format.mime_type())
+        .body(page.content)
 }
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
     let settings = Settings::load_from_config().await?;
-    let library = LibState::load_from_settings(&settings).await?;
+    let scan = ScanHandle::default();
+    let library = LibState::load_from_settings(&settings, &scan).await?;
+    let library_config = settings.library().cloned();
 
     tauri::Builder::default()
-        .manage(library)
+        .manage(library.clone())
         .manage(SettingsState::from_settings(settings))
+        .manage(scan.clone())
+        .setup(move |app| {
+            api::forward_scan_progress(app.handle(), &scan);
+            if let Some(dir) = app.path_resolver().resource_dir() {
+                archive::set_resource_dir(dir);
+            }
+
+            let mut lib = futures::executor::block_on(library.access_option());
+            if let Some(lib) = lib.as_mut()
+                && let Some(config) = &library_config
+            {
+                watcher::start(lib, config, library.clone(), app.handle());
+            }
+            drop(lib);
+
+            api::scan_in_background(app.handle(), library.clone());
+            Ok(())
+        })
         .register_uri_scheme_protocol("comic", get_comic_page)
         .invoke_handler(api::get_invoke_handler())
         .run(tauri::generate_context!())
@@ -0,0 +1,218 @@
+//! the chapter number as it's written in chapter names,
+//! e.g. `Vol.2 Ch.10.5`, `Berserk v01 c001`, `Ch 12a` or `Extra 3`
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::entities::ChapterNumber;
+
+/// tags like `[Group]`, `(Digital)` or `{v2}`, their numbers aren't the chapter number
+static BRACKETS_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[[^\]]*\]|\([^)]*\)|\{[^}]*\}").expect("invalid brackets regex")
+});
+
+static VOLUME_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(?:volume|vol|v)\.?\s*(\d+)\b").expect("invalid volume regex"));
+
+/// a number after a chapter keyword, which is preferred over other numbers in the name
+static CHAPTER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?:\b(?:chapter|chap|ch|c|episode|ep)\.?|#)\s*(\d+(?:\.\d+)?)([a-z])?\b")
+        .expect("invalid chapter regex")
+});
+
+/// a number after the series name, which ends the name or comes before the title,
+/// e.g. the `001` in `Berserk 001 - 100 Days Later`
+static SERIES_NUMBER_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(\d+(?:\.\d+)?)([a-z])?(?:\s*$|\s+[-–:]\s|:\s)")
+        .expect("invalid series number regex")
+});
+
+/// a number which stands on its own, with an optional single letter suffix (`12a` but not `20th`)
+static NUMBER_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b(\d+(?:\.\d+)?)([a-z])?\b").expect("invalid number regex"));
+
+static EXTRA_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\b(?:extra|special|omake|bonus|side story)s?\b").expect("invalid extra regex")
+});
+
+static PROLOGUE_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bprologue\b").expect("invalid prologue regex"));
+
+/// parse the volume, number, suffix and if it's an extra from a chapter name (without extension),
+/// returns `None` when the name contains none of them
+pub fn parse(name: &str) -> Option<ChapterNumber> {
+    let name = name.to_lowercase().replace('_', " ");
+    let mut name = BRACKETS_REGEX.replace_all(&name, " ").into_owned();
+
+    let mut volume = None;
+    if let Some(c) = VOLUME_REGEX.captures(&name) {
+        volume = c[1].parse().ok();
+        // the volume number mustn't be found as the chapter number again
+        let range = c.get(0).expect("whole match").range();
+        name.replace_range(range, " ");
+    }
+
+    let extra = EXTRA_REGEX.is_match(&name);
+    // without a chapter keyword, the number after the series name is used (e.g. `One Piece 1000`),
+    // the last one, because the series name can have a number too (e.g. `Kingdom 2 - 005`)
+    let number = CHAPTER_REGEX
+        .captures(&name)
+        .or_else(|| SERIES_NUMBER_REGEX.captures_iter(&name).last())
+        .or_else(|| NUMBER_REGEX.captures(&name));
+    let (number, suffix) = match number {
+        Some(c) => (c[1].parse().ok(), c.get(2).map(|s| s.as_str().to_owned())),
+        // the prologue comes before the first chapter
+        None if PROLOGUE_REGEX.is_match(&name) => (Some(0.0), None),
+        None => (None, None),
+    };
+
+    (volume.is_some() || number.is_some() || extra).then_some(ChapterNumber {
+        volume,
+        number,
+        suffix,
+        extra,
+    })
+}
+
+/// if the name contains a chapter keyword with a number, e.g. `Ch.1` or `#12`
+pub fn has_chapter_keyword(name: &str) -> bool {
+    CHAPTER_REGEX.is_match(&name.to_lowercase())
+}
+
+/// if the name ends with a number, like a series name with the chapter number, e.g. `Berserk 001`
+pub fn ends_with_number(name: &str) -> bool {
+    let name = BRACKETS_REGEX
+        .replace_all(&name.to_lowercase(), " ")
+        .into_owned();
+    SERIES_NUMBER_REGEX.is_match(&name)
+}
+
+/// key to sort chapter numbers, chapters without a volume come after the ones with one
+/// (usually they aren't released in a volume yet) and extras after the regular chapters
+pub fn sort_key(number: &ChapterNumber) -> (u32, bool, u64, Option<String>) {
+    (
+        number.volume.unwrap_or(u32::MAX),
+        number.extra,
+        // the bits of positive floats are in the same order as the floats themselves
+        number.number.unwrap_or_default().to_bits(),
+        number.suffix.clone(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn number(volume: Option<u32>, number: Option<f64>, suffix: Option<&str>) -> ChapterNumber {
+        ChapterNumber {
+            volume,
+            number,
+            suffix: suffix.map(Into::into),
+            extra: false,
+        }
+    }
+
+    fn extra(volume: Option<u32>, n: Option<f64>) -> ChapterNumber {
+        ChapterNumber {
+            extra: true,
+            ..number(volume, n, None)
+        }
+    }
+
+    #[test]
+    fn parse_real_world_names() {
+        let cases = [
+            ("Chapter 10", Some(number(None, Some(10.0), None))),
+            ("Chapter 10.5", Some(number(None, Some(10.5), None))),
+            ("Ch.10.5", Some(number(None, Some(10.5), None))),
+            ("ch1", Some(number(None, Some(1.0), None))),
+            ("c001 [Group]", Some(number(None, Some(1.0), None))),
+            ("#12", Some(number(None, Some(12.0), None))),
+            ("Episode 3", Some(number(None, Some(3.0), None))),
+            ("10", Some(number(None, Some(10.0), None))),
+            ("Vol.2 Ch.10.5", Some(number(Some(2), Some(10.5), None))),
+            (
+                "Vol. 02 Chapter 010",
+                Some(number(Some(2), Some(10.0), None)),
+            ),
+            ("Volume 3", Some(number(Some(3), None, None))),
+            ("Berserk v01 c001", Some(number(Some(1), Some(1.0), None))),
+            ("Berserk_v01_c002", Some(number(Some(1), Some(2.0), None))),
+            (
+                "Vol.01 Ch.001.5 - The Title",
+                Some(number(Some(1), Some(1.5), None)),
+            ),
+            ("Ch 12a", Some(number(None, Some(12.0), Some("a")))),
+            (
+                "Chapter 12b - The Title",
+                Some(number(None, Some(12.0), Some("b"))),
+            ),
+            ("One Piece 1000", Some(number(None, Some(1000.0), None))),
+            ("Kingdom 2 - 005", Some(number(None, Some(5.0), None))),
+            (
+                "Berserk 001 - 100 Days Later",
+                Some(number(None, Some(1.0), None)),
+            ),
+            (
+                "Kingdom 2 - 005: 3000 Soldiers",
+                Some(number(None, Some(5.0), None)),
+            ),
+            (
+                "Berserk 002 100 Days Later (Digital)",
+                Some(number(None, Some(2.0), None)),
+            ),
+            (
+                "Berserk 001 (Digital) (2021)",
+                Some(number(None, Some(1.0), None)),
+            ),
+            (
+                "[Scans] Chapter 7 [v2]",
+                Some(number(None, Some(7.0), None)),
+            ),
+            ("Ch. 001-002", Some(number(None, Some(1.0), None))),
+            ("20th Century Boys 003", Some(number(None, Some(3.0), None))),
+            ("Extra 3", Some(extra(None, Some(3.0)))),
+            ("Vol.2 Extra", Some(extra(Some(2), None))),
+            ("Special 1", Some(extra(None, Some(1.0)))),
+            ("Omake", Some(extra(None, None))),
+            ("Side Story 2", Some(extra(None, Some(2.0)))),
+            ("Prologue", Some(number(None, Some(0.0), None))),
+            ("Oneshot", None),
+            ("The Black Swordsman", None),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(parse(name), expected, "{name}");
+        }
+    }
+
+    #[test]
+    fn sort_chapter_numbers() {
+        let mut names = [
+            "Extra 1",
+            "Ch 12a",
+            "Chapter 20",
+            "Vol.2 Extra",
+            "Vol.2 Ch.10.5",
+            "Chapter 12",
+            "Vol.1 Ch.9",
+            "Vol.2 Ch.10",
+        ];
+        names.sort_by_key(|n| sort_key(&parse(n).unwrap()));
+
+        assert_eq!(
+            names,
+            [
+                "Vol.1 Ch.9",
+                "Vol.2 Ch.10",
+                "Vol.2 Ch.10.5",
+                "Vol.2 Extra",
+                "Chapter 12",
+                "Ch 12a",
+                "Chapter 20",
+                "Extra 1",
+            ]
+        );
+    }
+}
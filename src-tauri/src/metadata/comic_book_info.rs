@@ -0,0 +1,136 @@
+//! the ComicBookInfo json which ComicTagger stores in the comment of zip archives
+//! (<https://code.google.com/archive/p/comicbookinfo/wikis/Example.wiki>)
+
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::non_empty;
+use crate::entities::Metadata;
+
+const KEY: &str = "ComicBookInfo/1.0";
+
+#[derive(Deserialize)]
+struct Envelope {
+    #[serde(rename = "ComicBookInfo/1.0")]
+    info: ComicBookInfo,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(default, rename_all = "camelCase")]
+struct ComicBookInfo {
+    series: Option<String>,
+    title: Option<String>,
+    // taggers write the issue and volume either as a number or as a string
+    issue: Option<Value>,
+    volume: Option<Value>,
+    genre: Option<String>,
+    language: Option<String>,
+    comments: Option<String>,
+    credits: Vec<Credit>,
+}
+
+#[derive(Deserialize)]
+struct Credit {
+    person: String,
+    role: String,
+}
+
+/// checks if the archive comment contains ComicBookInfo, other comments are just text
+pub fn is_comic_book_info(comment: &str) -> bool {
+    comment.trim_start().starts_with('{') && comment.contains(KEY)
+}
+
+pub fn parse(json: &str) -> Result<Metadata> {
+    let info = serde_json::from_str::<Envelope>(json)
+        .context("invalid ComicBookInfo")?
+        .info;
+
+    let text = |t: &Option<String>| t.as_deref().and_then(non_empty);
+    let credit = |roles: &[&str]| {
+        let people = info
+            .credits
+            .iter()
+            .filter(|c| roles.iter().any(|r| c.role.eq_ignore_ascii_case(r)))
+            .map(|c| c.person.trim())
+            .collect::<Vec<_>>();
+
+        non_empty(&people.join(", "))
+    };
+
+    Ok(Metadata {
+        series: text(&info.series),
+        number: info.issue.as_ref().and_then(value_text),
+        volume: info
+            .volume
+            .as_ref()
+            .and_then(value_text)
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v >= 0),
+        title: text(&info.title),
+        author: credit(&["Writer"]),
+        penciller: credit(&["Penciller", "Artist"]),
+        summary: text(&info.comments),
+        genre: text(&info.genre),
+        language: text(&info.language),
+        manga: None,
+    })
+}
+
+fn value_text(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => non_empty(s),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comic_book_info() -> Result<()> {
+        let json = r#"{
+            "appID": "ComicTagger/1.0.0",
+            "lastModified": "2012-08-19 20:09:46",
+            "ComicBookInfo/1.0": {
+                "series": "Berserk",
+                "title": "The Black Swordsman",
+                "issue": 1,
+                "volume": "2",
+                "genre": "Dark Fantasy",
+                "language": "English",
+                "comments": " Guts & Puck ",
+                "credits": [
+                    { "person": "Kentaro Miura", "role": "Writer", "primary": true },
+                    { "person": "Kentaro Miura", "role": "Artist" }
+                ],
+                "tags": []
+            }
+        }"#;
+
+        assert!(is_comic_book_info(json));
+        assert!(!is_comic_book_info("created with some zip tool"));
+
+        assert_eq!(
+            parse(json)?,
+            Metadata {
+                series: Some("Berserk".into()),
+                number: Some("1".into()),
+                volume: Some(2),
+                title: Some("The Black Swordsman".into()),
+                author: Some("Kentaro Miura".into()),
+                penciller: Some("Kentaro Miura".into()),
+                summary: Some("Guts & Puck".into()),
+                genre: Some("Dark Fantasy".into()),
+                language: Some("English".into()),
+                manga: None,
+            }
+        );
+
+        assert!(parse(r#"{"other": {}}"#).is_err());
+
+        Ok(())
+    }
+}
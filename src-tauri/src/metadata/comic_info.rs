@@ -0,0 +1,189 @@
+//! the `ComicInfo.xml` of the ComicRack schema
+//! (<https://anansi-project.github.io/docs/comicinfo/intro>)
+
+use std::{fmt::Write, path::Path};
+
+use anyhow::{ensure, Result};
+use roxmltree::Document;
+
+use super::non_empty;
+use crate::entities::{Manga, Metadata};
+
+pub const FILE_NAME: &str = "ComicInfo.xml";
+
+/// elements which are created from the metadata when writing a `ComicInfo.xml`
+const METADATA_ELEMENTS: [&str; 10] = [
+    "Title",
+    "Series",
+    "Number",
+    "Volume",
+    "Summary",
+    "Writer",
+    "Penciller",
+    "Genre",
+    "LanguageISO",
+    "Manga",
+];
+
+/// checks if the file inside of an archive is a `ComicInfo.xml`
+pub fn is_comic_info(name: &str) -> bool {
+    Path::new(name)
+        .file_name()
+        .is_some_and(|n| n.to_string_lossy().eq_ignore_ascii_case(FILE_NAME))
+}
+
+pub fn parse(xml: &str) -> Result<Metadata> {
+    let doc = Document::parse(xml)?;
+    let root = doc.root_element();
+    ensure!(root.has_tag_name("ComicInfo"), "missing ComicInfo element");
+
+    let field = |name: &str| {
+        root.children()
+            .find(|n| n.has_tag_name(name))
+            .and_then(|n| n.text())
+            .and_then(non_empty)
+    };
+
+    Ok(Metadata {
+        series: field("Series"),
+        number: field("Number"),
+        // the schema uses -1 for an unknown volume
+        volume: field("Volume")
+            .and_then(|v| v.parse().ok())
+            .filter(|v| *v >= 0),
+        title: field("Title"),
+        author: field("Writer"),
+        penciller: field("Penciller"),
+        summary: field("Summary"),
+        genre: field("Genre"),
+        language: field("LanguageISO"),
+        manga: field("Manga").and_then(|m| Manga::parse(&m)),
+    })
+}
+
+/// create the `ComicInfo.xml` for the metadata,
+/// elements of an existing one which aren't part of the metadata (e.g. `Pages`) are kept
+pub fn write(metadata: &Metadata, existing: Option<&str>) -> Result<String> {
+    let mut kept = vec![];
+    if let Some(existing) = existing {
+        let doc = Document::parse(existing)?;
+        let root = doc.root_element();
+        ensure!(root.has_tag_name("ComicInfo"), "missing ComicInfo element");
+
+        kept = root
+            .children()
+            .filter(|n| n.is_element() && !METADATA_ELEMENTS.contains(&n.tag_name().name()))
+            // the elements are copied as they are, so nothing gets lost
+            .map(|n| &existing[n.range()])
+            .collect();
+    }
+
+    let values = [
+        metadata.title.clone(),
+        metadata.series.clone(),
+        metadata.number.clone(),
+        metadata.volume.map(|v| v.to_string()),
+        metadata.summary.clone(),
+        metadata.author.clone(),
+        metadata.penciller.clone(),
+        metadata.genre.clone(),
+        metadata.language.clone(),
+        metadata.manga.map(|m| m.as_str().to_owned()),
+    ];
+
+    let mut xml = String::from(concat!(
+        r#"<?xml version="1.0" encoding="utf-8"?>"#,
+        "\n",
+        r#"<ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+        "\n",
+    ));
+    for (name, value) in METADATA_ELEMENTS.iter().zip(values) {
+        if let Some(value) = value {
+            writeln!(xml, "  <{name}>{}</{name}>", escape(&value))?;
+        }
+    }
+    for element in kept {
+        writeln!(xml, "  {element}")?;
+    }
+    xml.push_str("</ComicInfo>\n");
+
+    Ok(xml)
+}
+
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_comic_info() -> Result<()> {
+        let xml = r#"<?xml version="1.0" encoding="utf-8"?>
+            <ComicInfo xmlns:xsd="http://www.w3.org/2001/XMLSchema" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
+                <Title>The Black Swordsman</Title>
+                <Series>Berserk</Series>
+                <Number>1</Number>
+                <Volume>-1</Volume>
+                <Summary> Guts &amp; Puck </Summary>
+                <Writer>Kentaro Miura</Writer>
+                <Penciller>Kentaro Miura</Penciller>
+                <Genre>Dark Fantasy</Genre>
+                <LanguageISO>en</LanguageISO>
+                <Manga>YesAndRightToLeft</Manga>
+                <Colorist></Colorist>
+            </ComicInfo>"#;
+
+        let meta = parse(xml)?;
+        assert_eq!(
+            meta,
+            Metadata {
+                series: Some("Berserk".into()),
+                number: Some("1".into()),
+                volume: None,
+                title: Some("The Black Swordsman".into()),
+                author: Some("Kentaro Miura".into()),
+                penciller: Some("Kentaro Miura".into()),
+                summary: Some("Guts & Puck".into()),
+                genre: Some("Dark Fantasy".into()),
+                language: Some("en".into()),
+                manga: Some(Manga::YesAndRightToLeft),
+            }
+        );
+
+        assert!(parse("<Other/>").is_err());
+        assert!(parse("not xml").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn write_comic_info() -> Result<()> {
+        let existing = r#"<ComicInfo>
+                <Title>Old Title</Title>
+                <Web>https://example.com</Web>
+                <Pages><Page Image="0" Type="FrontCover"/></Pages>
+            </ComicInfo>"#;
+        let meta = Metadata {
+            series: Some("Berserk".into()),
+            number: Some("1".into()),
+            title: Some("Guts & Puck".into()),
+            manga: Some(Manga::YesAndRightToLeft),
+            ..Default::default()
+        };
+
+        let xml = write(&meta, Some(existing))?;
+        assert_eq!(parse(&xml)?, meta);
+        assert!(!xml.contains("Old Title"));
+        assert!(xml.contains("<Web>https://example.com</Web>"));
+        assert!(xml.contains(r#"<Pages><Page Image="0" Type="FrontCover"/></Pages>"#));
+
+        assert_eq!(parse(&write(&meta, None)?)?, meta);
+        assert!(write(&meta, Some("<Other/>")).is_err());
+
+        Ok(())
+    }
+}
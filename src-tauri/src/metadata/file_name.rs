@@ -0,0 +1,192 @@
+//! the title, scanlation group and language in the common naming conventions of chapter files,
+//! e.g. `[Group] Berserk v01 c001 - The Black Swordsman [en]`,
+//! the volume and number are parsed by [`chapter_number`]
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use super::{chapter_number, non_empty};
+
+/// tags in square brackets or parentheses, e.g. `[Group]`, `[en]` or `(Digital)`
+static TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\[([^\]]*)\]|\(([^)]*)\)|\{([^}]*)\}").expect("invalid tag regex")
+});
+
+/// tags in square brackets which describe the release and not the group that made it
+static RELEASE_TAG_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^(?:v\d+|\d+p?|digital|hq|lq|raw|colou?r(?:ed)?)$")
+        .expect("invalid release tag regex")
+});
+
+static LANGUAGE_CODE_REGEX: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^([a-z]{2})(?:[-_]([a-z]{2}))?$").expect("invalid language code regex")
+});
+
+/// the separators between the number and the title of a chapter
+static SEPARATOR_REGEX: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s+[-–:]\s+|:\s+").expect("invalid separator regex"));
+
+/// ISO 639-1 codes and names of the languages which chapters are usually released in
+const LANGUAGES: [(&str, &str); 18] = [
+    ("en", "english"),
+    ("es", "spanish"),
+    ("fr", "french"),
+    ("de", "german"),
+    ("it", "italian"),
+    ("pt", "portuguese"),
+    ("ru", "russian"),
+    ("ja", "japanese"),
+    ("ko", "korean"),
+    ("zh", "chinese"),
+    ("id", "indonesian"),
+    ("vi", "vietnamese"),
+    ("th", "thai"),
+    ("pl", "polish"),
+    ("tr", "turkish"),
+    ("ar", "arabic"),
+    ("nl", "dutch"),
+    ("uk", "ukrainian"),
+];
+
+/// everything besides the number which can be found in the name of a chapter
+#[derive(Debug, Default, PartialEq)]
+pub struct FileName {
+    pub title: Option<String>,
+    pub group: Option<String>,
+    pub language: Option<String>,
+}
+
+/// parse the name of a chapter (without extension), the fields which can't be found are `None`
+pub fn parse(name: &str) -> FileName {
+    let name = name.replace('_', " ");
+
+    let mut group = None;
+    let mut language = None;
+    for tag in TAG_REGEX.captures_iter(&name) {
+        let square = tag.get(1).is_some();
+        let Some(text) = tag
+            .iter()
+            .skip(1)
+            .flatten()
+            .next()
+            .and_then(|t| non_empty(t.as_str()))
+        else {
+            continue;
+        };
+
+        if let Some(code) = language_code(&text) {
+            language.get_or_insert(code);
+        } else if square && group.is_none() && !RELEASE_TAG_REGEX.is_match(&text) {
+            group = Some(text);
+        }
+    }
+
+    let rest = TAG_REGEX.replace_all(&name, " ");
+    FileName {
+        title: title(&rest),
+        group,
+        language,
+    }
+}
+
+/// the part after the chapter number, e.g. `Ch.1 - The Black Swordsman`
+fn title(name: &str) -> Option<String> {
+    let parts = SEPARATOR_REGEX
+        .split(name)
+        .map(str::trim)
+        .collect::<Vec<_>>();
+
+    // the number comes from the part with a chapter keyword, the last one ending with a number
+    // or the first one with a number, the same way it's found by the chapter number parser
+    let number_part = parts
+        .iter()
+        .position(|p| chapter_number::has_chapter_keyword(p))
+        .or_else(|| {
+            parts
+                .iter()
+                .rposition(|p| chapter_number::ends_with_number(p))
+        })
+        .or_else(|| {
+            parts
+                .iter()
+                .position(|p| chapter_number::parse(p).is_some())
+        })?;
+
+    non_empty(&parts[number_part + 1..].join(" - "))
+}
+
+/// the normalized language code of a tag like `en`, `pt-BR` or `English`
+fn language_code(tag: &str) -> Option<String> {
+    let tag = tag.to_lowercase();
+    if let Some((code, _)) = LANGUAGES.iter().find(|(_, name)| *name == tag) {
+        return Some(code.to_string());
+    }
+
+    let c = LANGUAGE_CODE_REGEX.captures(&tag)?;
+    LANGUAGES.iter().find(|(code, _)| *code == &c[1])?;
+    Some(match c.get(2) {
+        Some(region) => format!("{}-{}", &c[1], region.as_str()),
+        None => c[1].to_owned(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file_name(title: Option<&str>, group: Option<&str>, language: Option<&str>) -> FileName {
+        FileName {
+            title: title.map(Into::into),
+            group: group.map(Into::into),
+            language: language.map(Into::into),
+        }
+    }
+
+    #[test]
+    fn parse_file_names() {
+        let cases = [
+            (
+                "[Group] Berserk v01 c001 - The Black Swordsman [en]",
+                file_name(Some("The Black Swordsman"), Some("Group"), Some("en")),
+            ),
+            (
+                "Vol.2 Ch.10.5: The Brand [Some Scans]",
+                file_name(Some("The Brand"), Some("Some Scans"), None),
+            ),
+            (
+                "Berserk - Ch.3 - Guardians of Desire (Digital) [pt-BR]",
+                file_name(Some("Guardians of Desire"), None, Some("pt-br")),
+            ),
+            (
+                "Chapter_012_-_The_Title_[English]",
+                file_name(Some("The Title"), None, Some("en")),
+            ),
+            (
+                "Chapter 3 - 100 Days Later",
+                file_name(Some("100 Days Later"), None, None),
+            ),
+            (
+                "Vol.2 - The Beginning [v2] [HQ]",
+                file_name(Some("The Beginning"), None, None),
+            ),
+            (
+                "[Group 2] Ch.7 [v3] [Other Group] [en] [fr]",
+                file_name(None, Some("Group 2"), Some("en")),
+            ),
+            ("Kingdom 2 - 005", file_name(None, None, None)),
+            (
+                "Berserk 001 - 100 Days Later",
+                file_name(Some("100 Days Later"), None, None),
+            ),
+            ("Berserk - Chapter 1", file_name(None, None, None)),
+            ("Chapter 1", file_name(None, None, None)),
+            ("The Black Swordsman", file_name(None, None, None)),
+            ("", file_name(None, None, None)),
+        ];
+
+        for (name, expected) in cases {
+            assert_eq!(parse(name), expected, "{name}");
+        }
+    }
+}
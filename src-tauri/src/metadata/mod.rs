@@ -0,0 +1,10 @@
+pub mod chapter_number;
+pub mod comic_book_info;
+pub mod comic_info;
+pub mod file_name;
+
+/// trim the text of a metadata field and skip it when it's empty
+fn non_empty(text: &str) -> Option<String> {
+    let text = text.trim();
+    (!text.is_empty()).then(|| text.to_owned())
+}
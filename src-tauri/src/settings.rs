@@ -22,6 +22,12 @@ pub struct LibraryConfig {
     pub id: usize,
     pub name: String,
     pub path: PathBuf,
+    /// write metadata edits back into the `ComicInfo.xml` of the archives
+    #[serde(default)]
+    pub write_comic_info: bool,
+    /// minutes between automatic rescans of the whole library
+    #[serde(default)]
+    pub rescan_interval: Option<u32>,
 }
 
 impl Default for Settings {
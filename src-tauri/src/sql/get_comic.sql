@@ -1,4 +1,4 @@
-SELECT c.id, dir_path, c.name, cover_path, is_manga, COUNT(*) as chapter_count,
+SELECT c.id, c.dir_path, c.name, c.cover_path, c.is_manga, COUNT(*) as chapter_count,
 -- gets the first one or
 COALESCE((
     -- get all chapter number which are not read (ordered)
@@ -6,9 +6,19 @@ COALESCE((
     FROM (SELECT * FROM chapter ORDER BY chapter_number)
     WHERE c.id = comic_id
     AND read < pages
-) -1, COUNT(*)) as read_chapters -- or the number of chapter when finished reading
+) -1, COUNT(*)) as read_chapters, -- or the number of chapter when finished reading
+m.series, m.author, m.penciller, m.summary, m.genre, m.language, m.manga
 FROM comic c
-JOIN chapter ON c.id = comic_id
+JOIN chapter ch ON c.id = ch.comic_id
+-- the metadata of the first chapter which has some
+LEFT JOIN chapter m ON m.id = (
+    SELECT id
+    FROM chapter
+    WHERE c.id = comic_id
+    AND COALESCE(series, author, penciller, summary, genre, language, manga) IS NOT NULL
+    ORDER BY chapter_number
+    LIMIT 1
+)
 WHERE c.id = (?1)
 GROUP BY c.id
 ORDER BY c.name
\ No newline at end of file
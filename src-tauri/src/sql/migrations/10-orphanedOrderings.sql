@@ -0,0 +1,2 @@
+-- the orderings of deleted comics were left behind, because foreign keys aren't enforced
+DELETE FROM chapterordering WHERE comic_id NOT IN (SELECT id FROM comic);
@@ -0,0 +1,2 @@
+ALTER TABLE chapter ADD COLUMN title TEXT;
+ALTER TABLE chapter ADD COLUMN author TEXT;
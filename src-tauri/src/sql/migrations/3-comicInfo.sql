@@ -0,0 +1,8 @@
+ALTER TABLE chapter ADD COLUMN series TEXT;
+ALTER TABLE chapter ADD COLUMN number TEXT;
+ALTER TABLE chapter ADD COLUMN volume INTEGER;
+ALTER TABLE chapter ADD COLUMN penciller TEXT;
+ALTER TABLE chapter ADD COLUMN summary TEXT;
+ALTER TABLE chapter ADD COLUMN genre TEXT;
+ALTER TABLE chapter ADD COLUMN language TEXT;
+ALTER TABLE chapter ADD COLUMN manga TEXT;
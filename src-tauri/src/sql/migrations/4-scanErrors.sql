@@ -0,0 +1,6 @@
+ALTER TABLE chapter ADD COLUMN broken BOOLEAN NOT NULL DEFAULT FALSE;
+
+CREATE TABLE scanerror (
+    path TEXT PRIMARY KEY,
+    message TEXT NOT NULL
+);
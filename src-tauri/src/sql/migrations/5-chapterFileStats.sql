@@ -0,0 +1,3 @@
+-- existing chapters have no stats yet, so they are read once more on the next scan
+ALTER TABLE chapter ADD COLUMN file_size INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE chapter ADD COLUMN modified INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1 @@
+ALTER TABLE chapter ADD COLUMN fingerprint TEXT;
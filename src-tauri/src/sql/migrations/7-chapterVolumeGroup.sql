@@ -0,0 +1 @@
+ALTER TABLE chapter ADD COLUMN volume_group TEXT;
@@ -0,0 +1,5 @@
+-- the chapter number as it's written in the chapter name, chapter_number is only the position
+ALTER TABLE chapter ADD COLUMN display_volume INTEGER;
+ALTER TABLE chapter ADD COLUMN display_number REAL;
+ALTER TABLE chapter ADD COLUMN display_suffix TEXT;
+ALTER TABLE chapter ADD COLUMN is_extra BOOLEAN NOT NULL DEFAULT FALSE;
@@ -0,0 +1,4 @@
+-- the parts of the chapter name, separate from the title and language of the ComicInfo.xml
+ALTER TABLE chapter ADD COLUMN name_title TEXT;
+ALTER TABLE chapter ADD COLUMN scanlation_group TEXT;
+ALTER TABLE chapter ADD COLUMN name_language TEXT;
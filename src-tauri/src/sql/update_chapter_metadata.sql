@@ -0,0 +1,5 @@
+UPDATE chapter SET
+    title = (?2), author = (?3), series = (?4), number = (?5), volume = (?6),
+    penciller = (?7), summary = (?8), genre = (?9), language = (?10), manga = (?11),
+    file_size = (?12), modified = (?13), fingerprint = (?14)
+WHERE id = (?1);
@@ -1,3 +1,24 @@
-INSERT INTO chapter (file_path, chapter_number, read, pages, comic_id, name)
-VALUES (?1, ?2, ?3, ?4, ?5, ?6)
-ON CONFLICT(file_path) DO UPDATE SET chapter_number=excluded.chapter_number;
\ No newline at end of file
+INSERT INTO chapter (
+    file_path, chapter_number, read, pages, comic_id, name,
+    title, author, series, number, volume, penciller, summary, genre, language, manga, broken,
+    file_size, modified, fingerprint, volume_group,
+    display_volume, display_number, display_suffix, is_extra,
+    name_title, scanlation_group, name_language
+)
+VALUES (
+    ?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13,
+    ?14, ?15, ?16, ?17, ?18, ?19, ?20, ?21, ?22, ?23, ?24, ?25,
+    ?26, ?27, ?28
+)
+-- the read progress is kept, everything else can change when the chapter is read again
+ON CONFLICT(file_path) DO UPDATE SET
+    chapter_number=excluded.chapter_number, pages=excluded.pages, name=excluded.name,
+    title=excluded.title, author=excluded.author, series=excluded.series, number=excluded.number,
+    volume=excluded.volume, penciller=excluded.penciller, summary=excluded.summary,
+    genre=excluded.genre, language=excluded.language, manga=excluded.manga, broken=excluded.broken,
+    file_size=excluded.file_size, modified=excluded.modified,
+    fingerprint=excluded.fingerprint, volume_group=excluded.volume_group,
+    display_volume=excluded.display_volume, display_number=excluded.display_number,
+    display_suffix=excluded.display_suffix, is_extra=excluded.is_extra,
+    name_title=excluded.name_title, scanlation_group=excluded.scanlation_group,
+    name_language=excluded.name_language;
@@ -1 +1,2 @@
+pub mod natural_sort;
 pub mod str_error;
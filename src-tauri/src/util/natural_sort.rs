@@ -0,0 +1,81 @@
+use std::{cmp::Ordering, iter::Peekable, str::Chars};
+
+/// compare two strings the way a human would, so numbers inside of them
+/// are compared by their value (`2.jpg` < `10.jpg`) and letters ignore their case
+pub fn natural_cmp(a: &str, b: &str) -> Ordering {
+    let mut a_chars = a.chars().peekable();
+    let mut b_chars = b.chars().peekable();
+
+    loop {
+        let ord = match (a_chars.peek(), b_chars.peek()) {
+            (None, None) => break,
+            (None, Some(_)) => return Ordering::Less,
+            (Some(_), None) => return Ordering::Greater,
+            (Some(x), Some(y)) if x.is_ascii_digit() && y.is_ascii_digit() => {
+                cmp_numbers(&take_number(&mut a_chars), &take_number(&mut b_chars))
+            }
+            (Some(&x), Some(&y)) => {
+                a_chars.next();
+                b_chars.next();
+                x.to_lowercase().cmp(y.to_lowercase())
+            }
+        };
+
+        if ord != Ordering::Equal {
+            return ord;
+        }
+    }
+
+    // fallback for strings which only differ in case or leading zeros
+    a.cmp(b)
+}
+
+fn take_number(chars: &mut Peekable<Chars>) -> String {
+    let mut number = String::new();
+    while let Some(c) = chars.next_if(char::is_ascii_digit) {
+        number.push(c);
+    }
+    number
+}
+
+/// compare two digit strings by their value, without the risk of overflows
+fn cmp_numbers(a: &str, b: &str) -> Ordering {
+    let a = a.trim_start_matches('0');
+    let b = b.trim_start_matches('0');
+
+    a.len().cmp(&b.len()).then_with(|| a.cmp(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn natural_order() {
+        let mut names = vec![
+            "10.jpg",
+            "2.jpg",
+            "1.jpg",
+            "page 11.png",
+            "Page 3.png",
+            "001.jpg",
+            "img_99999999999999999999999.png",
+            "img_5.png",
+        ];
+        names.sort_by(|a, b| natural_cmp(a, b));
+
+        assert_eq!(
+            names,
+            [
+                "001.jpg",
+                "1.jpg",
+                "2.jpg",
+                "10.jpg",
+                "img_5.png",
+                "img_99999999999999999999999.png",
+                "Page 3.png",
+                "page 11.png",
+            ]
+        );
+    }
+}
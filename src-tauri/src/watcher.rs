@@ -0,0 +1,169 @@
+//! keeps the library up to date while files are changed inside of the library directory
+//! and with periodic rescans
+
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use notify_debouncer_mini::{new_debouncer, notify::RecursiveMode, DebounceEventResult};
+use tauri::{AppHandle, Manager, Runtime};
+use tokio::{
+    sync::{mpsc, oneshot},
+    time::{self, Instant},
+};
+
+use crate::{api::LibState, entities::LibraryChanges, library::Library, settings::LibraryConfig};
+
+/// time without new events before the changed comics are scanned,
+/// so copying a chapter doesn't trigger a scan for every written block
+const DEBOUNCE_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// start everything which keeps the loaded library up to date,
+/// without a watcher the library is only updated by the periodic and manual rescans
+pub fn start<R: Runtime>(
+    library: &mut Library,
+    config: &LibraryConfig,
+    state: LibState,
+    app: AppHandle<R>,
+) {
+    schedule_rescan(library, config.rescan_interval, state.clone(), app.clone());
+    if let Err(e) = watch_library(library, state, app) {
+        eprintln!("can't watch the library for changes: {e:#}");
+    }
+}
+
+/// watch the directory of the library and rescan the comics with changes inside of them,
+/// the watcher stops when the library is dropped
+fn watch_library<R: Runtime>(
+    library: &mut Library,
+    state: LibState,
+    app: AppHandle<R>,
+) -> Result<()> {
+    let root = library.path.clone();
+    let (sender, mut receiver) = mpsc::unbounded_channel::<HashSet<PathBuf>>();
+
+    let event_root = root.clone();
+    let mut debouncer = new_debouncer(DEBOUNCE_TIMEOUT, move |res: DebounceEventResult| {
+        match res {
+            Ok(events) => {
+                let dirs = events
+                    .iter()
+                    .filter_map(|e| comic_dir(&event_root, &e.path))
+                    .collect::<HashSet<_>>();
+
+                if !dirs.is_empty() {
+                    // sending only fails when the task below is gone
+                    let _ = sender.send(dirs);
+                }
+            }
+            Err(e) => eprintln!("library watcher failed: {e}"),
+        }
+    })?;
+    debouncer.watcher().watch(&root, RecursiveMode::Recursive)?;
+
+    tauri::async_runtime::spawn(async move {
+        while let Some(dirs) = receiver.recv().await {
+            // events can still arrive after another library has been selected
+            let Some(mut lib) = state.scanner().await.filter(|l| l.path == root) else {
+                continue;
+            };
+
+            // the directories are updated together, so a renamed comic is recognized
+            let dirs = dirs.into_iter().collect::<Vec<_>>();
+            match lib.update_comics(&dirs).await {
+                Ok(changes) => emit_changes(&app, changes),
+                Err(e) => eprintln!("failed updating changed comics: {e:#}"),
+            }
+        }
+    });
+
+    library.watcher = Some(debouncer);
+    Ok(())
+}
+
+/// rescan the whole library every few minutes, which also finds changes the watcher can't see
+/// (e.g. on network drives), a previously scheduled rescan is stopped
+pub fn schedule_rescan<R: Runtime>(
+    library: &mut Library,
+    minutes: Option<u32>,
+    state: LibState,
+    app: AppHandle<R>,
+) {
+    // dropping the sender stops the old task
+    library.rescan_timer = None;
+    let Some(minutes) = minutes.filter(|m| *m > 0) else {
+        return;
+    };
+
+    let root = library.path.clone();
+    let (stop, mut stopped) = oneshot::channel();
+    let period = Duration::from_secs(u64::from(minutes) * 60);
+
+    tauri::async_runtime::spawn(async move {
+        let mut interval = time::interval_at(Instant::now() + period, period);
+        loop {
+            tokio::select! {
+                _ = interval.tick() => (),
+                _ = &mut stopped => break,
+            }
+
+            let Some(mut lib) = state.scanner().await.filter(|l| l.path == root) else {
+                break;
+            };
+
+            match lib.update().await {
+                Ok(changes) => emit_changes(&app, changes),
+                Err(e) => eprintln!("periodic rescan failed: {e:#}"),
+            }
+        }
+    });
+
+    library.rescan_timer = Some(stop);
+}
+
+/// tell the frontend which comics need to be reloaded
+pub fn emit_changes<R: Runtime>(app: &AppHandle<R>, changes: LibraryChanges) {
+    if !changes.is_empty()
+        && let Err(e) = app.emit_all("comics_reloaded", changes)
+    {
+        eprintln!("can't send comics_reloaded event: {e}");
+    }
+}
+
+/// the comic directory (relative to the library) which contains the changed path,
+/// hidden entries like the `.comicdb` are ignored
+fn comic_dir(root: &Path, path: &Path) -> Option<PathBuf> {
+    let first = path.strip_prefix(root).ok()?.components().next()?;
+    let name = first.as_os_str().to_string_lossy();
+
+    (!name.starts_with('.')).then(|| PathBuf::from(&*name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn comic_dir_of_changes() {
+        let root = Path::new("/library");
+
+        assert_eq!(
+            comic_dir(root, Path::new("/library/Berserk/Chapter 1.cbz")),
+            Some(PathBuf::from("Berserk"))
+        );
+        assert_eq!(
+            comic_dir(root, Path::new("/library/Berserk/Volume 1/001.png")),
+            Some(PathBuf::from("Berserk"))
+        );
+        assert_eq!(
+            comic_dir(root, Path::new("/library/Berserk")),
+            Some(PathBuf::from("Berserk"))
+        );
+        assert_eq!(comic_dir(root, Path::new("/library/.comicdb")), None);
+        assert_eq!(comic_dir(root, Path::new("/library")), None);
+        assert_eq!(comic_dir(root, Path::new("/other/Berserk")), None);
+    }
+}
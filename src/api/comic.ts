@@ -2,6 +2,9 @@ import { invoke } from "@tauri-apps/api";
 import type { Comic } from "../entities/Comic";
 import type { Chapter } from "../entities/Chapter";
 import type { ChapterOrdering } from "../entities/ChapterOrdering";
+import type { Metadata } from "../entities/Metadata";
+import type { LibraryChanges } from "../entities/LibraryChanges";
+import type { ScanError } from "../entities/ScanError";
 
 export function getAllComics(): Promise<Comic[]> {
     return invoke("all_comics");
@@ -15,6 +18,22 @@ export function getComicWithChapters(comicId: number): Promise<Comic> {
     return invoke("comic_with_chapters", { id: comicId })
 }
 
+export function rescanLibrary(): Promise<LibraryChanges> {
+    return invoke("rescan_library");
+}
+
+export function rescanComic(comicId: number): Promise<LibraryChanges> {
+    return invoke("rescan_comic", { id: comicId });
+}
+
+export function cancelScan(): Promise<void> {
+    return invoke("cancel_scan");
+}
+
+export function getScanReport(): Promise<ScanError[]> {
+    return invoke("scan_report");
+}
+
 export function getChapterByNumber(comicId: number, chapterNumber: number): Promise<Chapter> {
     return invoke("chapter", { comicId, chapterNumber });
 }
@@ -23,6 +42,10 @@ export function updateChapterReadStatus(chapterId: number, page: number): Promis
     return invoke("chapter_page_update", { id: chapterId, page });
 }
 
+export function updateChapterMetadata(chapterId: number, metadata: Metadata): Promise<void> {
+    return invoke("chapter_metadata_update", { id: chapterId, metadata });
+}
+
 export function getChapterOrderings(comicId: number): Promise<ChapterOrdering[]> {
     return invoke("chapter_orderings", { comicId });
 }
@@ -37,4 +60,8 @@ export function deleteChapterOrdering(id: number): Promise<void> {
 
 export function updateChapterOrdering(ordering: ChapterOrdering): Promise<void> {
     return invoke("update_ordering", { ordering })
+}
+
+export function previewChapterOrdering(comicId: number, orderings: ChapterOrdering[]): Promise<Chapter[]> {
+    return invoke("preview_ordering", { comicId, orderings })
 }
\ No newline at end of file
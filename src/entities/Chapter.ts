@@ -1,3 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { ChapterNumber } from "./ChapterNumber";
+import type { Metadata } from "./Metadata";
 
-export interface Chapter { id: number, path: string, name: string, chapter_number: number, read: number, pages: number, comic_id: number, }
\ No newline at end of file
+export interface Chapter { id: number, path: string, name: string, chapter_number: number, display_number: ChapterNumber | null, volume_group: string | null, name_title: string | null, scanlation_group: string | null, name_language: string | null, metadata: Metadata, read: number, pages: number, broken: boolean, comic_id: number, }
\ No newline at end of file
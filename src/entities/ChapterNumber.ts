@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ChapterNumber { volume: number | null, number: number | null, suffix: string | null, extra: boolean, }
\ No newline at end of file
@@ -1,4 +1,5 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 import type { Chapter } from "./Chapter";
+import type { Metadata } from "./Metadata";
 
-export interface Comic { id: number, dir_path: string, name: string, cover_path: string | null, is_manga: boolean, chapters: Array<Chapter>, chapter_count: number | null, chapter_read: number | null, }
\ No newline at end of file
+export interface Comic { id: number, dir_path: string, name: string, cover_path: string | null, is_manga: boolean, metadata: Metadata, chapters: Array<Chapter>, chapter_count: number | null, chapter_read: number | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface LibraryChanges { added_comics: Array<number>, updated_comics: Array<number>, deleted_comics: Array<number>, }
\ No newline at end of file
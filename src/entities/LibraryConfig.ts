@@ -1,3 +1,3 @@
 // This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
 
-export interface LibraryConfig { id: number, name: string, path: string, }
\ No newline at end of file
+export interface LibraryConfig { id: number, name: string, path: string, write_comic_info: boolean, rescan_interval: number | null, }
@@ -0,0 +1,4 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+import type { Manga } from "./Manga";
+
+export interface Metadata { series: string | null, number: string | null, volume: number | null, title: string | null, author: string | null, penciller: string | null, summary: string | null, genre: string | null, language: string | null, manga: Manga | null, }
\ No newline at end of file
@@ -0,0 +1,3 @@
+// This file was generated by [ts-rs](https://github.com/Aleph-Alpha/ts-rs). Do not edit this file manually.
+
+export interface ScanProgress { comics_discovered: number, comics_processed: number, chapters_processed: number, current_file: string | null, errors: number, finished: boolean, cancelled: boolean, }
\ No newline at end of file
@@ -3,12 +3,14 @@ import { writable } from "svelte/store";
 import type { Settings } from "./entities/Settings";
 import { getSettings } from "./api/settings";
 import type { Comic } from "./entities/Comic";
+import type { ScanProgress } from "./entities/ScanProgress";
 import { getAllComics, getChapterByNumber } from "./api/comic";
 
 
 
 export const settings = writable<Settings | null>()
 export const comics = writable<Comic[]>([])
+export const scanProgress = writable<ScanProgress | null>(null)
 
 
 export function reloadSettings(): void {
@@ -37,3 +39,4 @@ reloadComics();
 
 // reloads the comic store when the event is received
 listen("comics_reloaded", _ => reloadComics());
+listen<ScanProgress>("scan_progress", e => scanProgress.set(e.payload));